@@ -1,39 +1,68 @@
-use std::env;
 use std::fmt::{Debug, Display, Formatter};
 use std::ops::Deref;
 use std::sync::Mutex;
+use std::{fs, io};
 
-use chrono::Utc;
+use chrono::{DateTime, Days, Local, NaiveDate, Utc};
 use crossterm::event::{KeyCode, KeyEvent, KeyEventKind};
 use once_cell::sync::Lazy;
-use uuid::Uuid;
 
 use crate::app::ProjectState::Working;
-use crate::app_config::AppConfig;
+use crate::app_config::{AppConfig, ShutdownAction};
+use crate::history::{self, RecordField};
 use crate::input::filter_mode::FilterMode;
 use crate::input::handler::InputHandler;
 use crate::input::normal_mode::NormalMode;
+use crate::input::prompt_mode::PromptMode;
 use crate::log::log;
+use crate::paths::Paths;
 use crate::report::Report;
-use crate::repository::model::{ProjectState, TimeKind, TimeSegment, WorkRecord};
-use crate::repository::work_record::WorkRecordRepository;
+use crate::repository::error;
+use crate::repository::lock::InstanceLock;
+use crate::repository::model::{InvalidTransition, ProjectState, TimeKind, WorkRecord};
+use crate::repository::trash::{Trash, Trashed};
+use crate::repository::work_record::{self, WorkRecordRepository};
+use crate::time::{format_local, local_midnight, parse_local};
+use crate::undo::{Step, UndoStack};
 use crate::widgets::list::StatefulList;
-use crate::widgets::week_picker::WeekPickerState;
+use crate::widgets::period_picker::PeriodPickerState;
+use crate::widgets::prompt::PromptState;
 use crate::SETTINGS;
+use tui::widgets::ListState;
 
-static WORK_RECORD_REPO: Lazy<Mutex<WorkRecordRepository>> = Lazy::new(|| {
+static WORK_RECORD_REPO: Lazy<Mutex<Box<dyn WorkRecordRepository>>> = Lazy::new(|| {
+    let storage = SETTINGS
+        .read()
+        .expect("could not acquire read lock on app settings")
+        .storage
+        .clone();
     Mutex::new(
-        WorkRecordRepository::new(
-            &env::current_dir()
-                .expect("cwd is not set")
-                .into_os_string()
-                .into_string()
-                .expect("could not convert cwd to string"),
-        )
-        .expect("could not create database"),
+        work_record::open(&Paths::current().data_dir, &storage).expect("could not create database"),
     )
 });
 
+/// the reason of pauses started by one of the `breaks.windows`
+const LOCK_SCREEN: &str = "lock screen";
+/// the first option when asked why the work is paused
+const NO_REASON: &str = "no reason";
+
+/// Shows `record` with the working time of the kinds configured for the profile in use.
+pub fn describe(record: &WorkRecord) -> impl Display + '_ {
+    let config = SETTINGS
+        .read()
+        .expect("could not acquire read lock on app settings")
+        .clone();
+    record.display(move |kind| config.is_working(kind))
+}
+
+/// Makes sure no other instance works on the same data directory while the lock is held.
+/// Commands that change records take it as well, since the running app would overwrite their
+/// changes with its own state of the active record.
+pub fn lock_instance(paths: &Paths) -> io::Result<InstanceLock> {
+    fs::create_dir_all(&paths.data_dir)?;
+    InstanceLock::acquire(&paths.data_dir)
+}
+
 pub trait Focusable {
     fn on_input(&mut self, event: &KeyEvent);
 }
@@ -42,6 +71,7 @@ pub trait Focusable {
 pub enum Mode {
     Normal(NormalMode),
     Filter(FilterMode),
+    Prompt(PromptMode),
 }
 
 #[derive(PartialEq, Debug)]
@@ -49,92 +79,166 @@ pub enum Focus {
     Projects,
     Log,
     Report,
+    History,
 }
 
 #[derive(Debug)]
 pub struct ActiveProject {
     record: WorkRecord,
+    keep_open: bool,
 }
 
 impl Drop for ActiveProject {
     fn drop(&mut self) {
-        self.stop();
+        if !self.keep_open {
+            self.stop();
+        }
+    }
+}
+
+impl From<WorkRecord> for ActiveProject {
+    fn from(record: WorkRecord) -> Self {
+        ActiveProject {
+            record,
+            keep_open: false,
+        }
     }
 }
 
 impl ActiveProject {
-    pub fn load_previous() -> Option<ActiveProject> {
-        WORK_RECORD_REPO
-            .lock()
-            .unwrap()
-            .get_latest()
-            .map(|record| ActiveProject { record })
-    }
-
-    pub fn new(name: String) -> ActiveProject {
-        let start = Utc::now();
-        let work_record = WorkRecord {
-            id: Uuid::new_v4().to_string(),
-            name,
-            start,
-            end: None,
-            state: Working,
-            segments: vec![TimeSegment {
-                start,
-                end: None,
-                kind: TimeKind::Productive,
-            }],
+    /// Loads the latest record, which is still open if the app was not shut down properly.
+    pub fn load_previous() -> Option<WorkRecord> {
+        WORK_RECORD_REPO.lock().unwrap().get_latest()
+    }
+
+    /// Starts working on a new record, which begins where the previous one was stopped.
+    pub fn start(record: WorkRecord) -> ActiveProject {
+        log!("{}", describe(&record));
+        let project = ActiveProject::from(record);
+        project.save();
+        project
+    }
+
+    pub fn begin_pause(&mut self, reason: Option<String>) {
+        self.pause_at(Utc::now(), reason);
+    }
+
+    pub fn pause_at(&mut self, at: DateTime<Utc>, reason: Option<String>) {
+        let result = self.record.pause_at(at, reason.clone());
+        if self.saved(result) {
+            match reason {
+                Some(reason) => log!("𝄽 pausing work ({})", reason),
+                None => log!("𝄽 pausing work"),
+            }
+        }
+    }
+
+    pub fn resume_work(&mut self) {
+        let result = self.record.transition(Working, Utc::now());
+        if self.saved(result) {
+            log!("♪ resuming work");
+        }
+    }
+
+    /// Continues the record with time of another kind, e.g. a meeting.
+    pub fn switch_kind(&mut self, kind: TimeKind) {
+        let result = self.record.switch_kind(kind.clone(), Utc::now());
+        if self.saved(result) {
+            log!("♪ continuing with {}", kind.name());
+        }
+    }
+
+    /// Saves the record after it changed its state, invalid changes are logged and ignored.
+    fn saved(&mut self, result: Result<(), InvalidTransition>) -> bool {
+        match result {
+            Ok(()) => {
+                self.save();
+                true
+            }
+            Err(e) => {
+                log!("⚠ {} {}", self.record.name, e);
+                false
+            }
+        }
+    }
+
+    /// Continues the record in a new one at midnight, so records never span several days.
+    pub fn roll_over(&mut self, now: DateTime<Utc>) {
+        if self.record.state == ProjectState::Done {
+            return;
+        }
+        let mut parts = self.record.split_at_midnight(now);
+        let Some(current) = parts.pop() else {
+            return;
         };
-        log!("{}", work_record);
-        if let Err(e) = WORK_RECORD_REPO
-            .lock()
-            .unwrap()
-            .persist(work_record.clone())
-        {
-            log!("failed to save work record for {}: {}", work_record.name, e);
+        if parts.is_empty() {
+            return;
+        }
+        for part in parts {
+            log!("{}", describe(&part));
+            if let Err(e) = WORK_RECORD_REPO.lock().unwrap().persist(part) {
+                log!("failed to save work record for {}: {}", self.record.name, e);
+            }
         }
+        self.record = current;
+        log!("continuing {} on a new day", self.record.name);
+        self.save();
+    }
 
-        ActiveProject {
-            record: work_record,
+    pub fn insert_pause(&mut self, from: DateTime<Utc>, to: DateTime<Utc>) {
+        self.record.insert_pause(from, to);
+        self.save();
+    }
+
+    /// Pauses the record and leaves it open when dropped, so it can be resumed on the next start.
+    pub fn pause_and_keep_open(&mut self) {
+        if self.record.state == Working {
+            self.begin_pause(None);
         }
+        self.keep_open = true;
     }
 
-    pub fn begin_pause(&mut self) {
-        log!("𝄽 pausing work");
-        self.record.state = ProjectState::Paused;
-        if let Some(last_segment) = self.record.segments.last_mut() {
-            last_segment.finish();
+    /// Adds `tags` to the record, it is only saved if any of them is new.
+    pub fn add_tags(&mut self, tags: &[String]) {
+        let mut added = false;
+        for tag in tags {
+            added |= self.record.tags.insert(tag.clone());
+        }
+        if added {
+            log!(
+                "# {} is tagged {}",
+                self.record.name,
+                RecordField::Tags.value(&self.record)
+            );
+            self.save();
         }
-        self.record.segments.push(TimeSegment {
-            start: Utc::now(),
-            end: None,
-            kind: TimeKind::Pause,
-        });
     }
 
-    pub fn resume_work(&mut self) {
-        log!("♪ resuming work");
-        self.record.state = Working;
-        if let Some(last_segment) = self.record.segments.last_mut() {
-            last_segment.finish();
-        }
-        self.record.segments.push(TimeSegment {
-            start: Utc::now(),
-            end: None,
-            kind: TimeKind::Productive,
-        });
+    /// Marks the record as still active, so a crash can be closed at the last heartbeat.
+    pub fn heartbeat(&mut self, now: DateTime<Utc>) {
+        if self.record.state == ProjectState::Done {
+            return;
+        }
+        self.record.last_seen = Some(now);
+        self.save();
     }
 
     pub fn stop(&mut self) {
+        self.stop_at(Utc::now());
+    }
+
+    /// Stops the record at `end`, records that are already done are left as they are.
+    pub fn stop_at(&mut self, end: DateTime<Utc>) {
         if self.record.state == ProjectState::Done {
             return;
         }
-        self.record.state = ProjectState::Done;
-        if let Some(last_segment) = self.record.segments.last_mut() {
-            last_segment.finish();
-            self.record.end = last_segment.end;
+        let result = self.record.transition(ProjectState::Done, end);
+        if self.saved(result) {
+            log!("{}", describe(&self.record));
         }
-        log!("{}", self.record);
+    }
+
+    fn save(&self) {
         if let Err(e) = WORK_RECORD_REPO
             .lock()
             .unwrap()
@@ -147,31 +251,185 @@ impl ActiveProject {
 
 impl Display for ActiveProject {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        std::fmt::Display::fmt(&self.record, f)
+        std::fmt::Display::fmt(&describe(&self.record), f)
     }
 }
 
 #[derive(Default, Debug)]
 pub struct ReportState {
-    pub weekpicker: WeekPickerState,
+    pub picker: PeriodPickerState,
     pub report: Option<Report>,
+    pub error: Option<String>,
+    /// the records of the report, to pick one to describe
+    records: Vec<WorkRecord>,
+    /// list every record below the totals
+    details: bool,
+    /// sum up the time per tag instead of per client
+    by_tag: bool,
+    /// list the breaks per reason and day below the totals
+    breaks: bool,
 }
 
 impl ReportState {
     pub fn calculate(&mut self) {
-        let (start, _) = self.weekpicker.start_and_end();
-        let records = WORK_RECORD_REPO.lock().unwrap().find_week(&start);
-        if let Ok(records) = records {
-            self.report = Some(Report::new_pct(&records))
-        } else {
-            self.report = None;
+        let (start, end) = self.picker.start_and_end();
+        let records = WORK_RECORD_REPO.lock().unwrap().find_range(
+            local_midnight(start),
+            local_midnight(end + Days::new(1)),
+            None,
+        );
+        match records {
+            Ok(records) => {
+                let config = SETTINGS.read().unwrap().clone();
+                let working = |kind: &TimeKind| config.is_working(kind);
+                let mut report = if self.by_tag {
+                    Report::new_by_tag(&records, start, end, &working)
+                } else {
+                    Report::new_pct(&records, start, end, &config)
+                };
+                if self.details {
+                    report.add_details(&records, start, end, &working);
+                }
+                if self.breaks {
+                    report.add_breaks(&records, start, end);
+                }
+                self.report = Some(report);
+                self.records = records;
+                self.error = None;
+            }
+            Err(e) => {
+                log!("⚠ failed to load {} - {}: {}", start, end, e);
+                self.report = None;
+                self.records = vec![];
+                self.error = Some(e.to_string());
+            }
         }
     }
 }
 
+/// The records of one day, to look through and edit them.
+#[derive(Debug)]
+pub struct HistoryState {
+    pub day: NaiveDate,
+    pub records: Vec<WorkRecord>,
+    pub selection: ListState,
+    /// the working time of the day
+    pub total: chrono::Duration,
+    pub error: Option<String>,
+}
+
+impl Default for HistoryState {
+    fn default() -> Self {
+        HistoryState {
+            day: Local::now().date_naive(),
+            records: vec![],
+            selection: ListState::default(),
+            total: chrono::Duration::zero(),
+            error: None,
+        }
+    }
+}
+
+impl HistoryState {
+    pub fn load(&mut self) {
+        let from = local_midnight(self.day);
+        let to = local_midnight(self.day + Days::new(1));
+        match WORK_RECORD_REPO.lock().unwrap().find_range(from, to, None) {
+            Ok(mut records) => {
+                records.sort_by_key(|record| record.start);
+                let config = SETTINGS.read().unwrap().clone();
+                self.total = records
+                    .iter()
+                    .map(|record| {
+                        record.calculate_duration_between(from, to, |kind| config.is_working(kind))
+                    })
+                    .fold(chrono::Duration::zero(), |sum, duration| sum + duration);
+                let selected = match self.selection.selected() {
+                    _ if records.is_empty() => None,
+                    Some(index) => Some(index.min(records.len() - 1)),
+                    None => Some(0),
+                };
+                self.selection.select(selected);
+                self.records = records;
+                self.error = None;
+            }
+            Err(e) => {
+                log!("⚠ failed to load the records of {}: {}", self.day, e);
+                self.records = vec![];
+                self.selection.select(None);
+                self.error = Some(e.to_string());
+            }
+        }
+    }
+
+    fn selected(&self) -> Option<&WorkRecord> {
+        self.selection
+            .selected()
+            .and_then(|index| self.records.get(index))
+    }
+
+    fn select_next(&mut self, offset: isize) {
+        if self.records.is_empty() {
+            return;
+        }
+        let len = self.records.len() as isize;
+        let selected = self.selection.selected().unwrap_or(0) as isize;
+        self.selection
+            .select(Some((selected + offset).rem_euclid(len) as usize));
+    }
+
+    fn show_day(&mut self, day: NaiveDate) {
+        self.day = day;
+        self.selection.select(None);
+        self.load();
+    }
+}
+
+#[derive(Debug)]
+pub struct Prompt {
+    pub state: PromptState,
+    action: PromptAction,
+}
+
+/// What to do once the user answered the open [Prompt].
+#[derive(Debug)]
+enum PromptAction {
+    /// decide what to do with a record that was left open by a crash
+    Recover(WorkRecord),
+    /// close a record that was left open by a crash at a time entered by the user
+    RecoverAt(WorkRecord),
+    /// continue with another profile
+    SwitchProfile,
+    /// report on the days entered by the user
+    ReportRange,
+    /// pause the active record for the selected reason, the time it was asked at
+    Pause(DateTime<Utc>),
+    /// continue the active record with time of the selected kind
+    SwitchKind,
+    /// pick one of the records to edit
+    ChooseRecord(Vec<WorkRecord>),
+    /// set a field of the record to the text entered by the user
+    Edit(WorkRecord, RecordField),
+    /// pick the field of the record to edit
+    ChooseField(WorkRecord),
+    /// add a record of the selected project on the day of the history
+    NewRecord,
+    /// add a record of the project at the times entered by the user
+    NewRecordAt(String),
+    /// split the record at the time entered by the user
+    SplitAt(WorkRecord),
+    /// start the project at the time entered by the user
+    StartAt(String),
+    /// split the record at the time, the rest goes to the selected project
+    SplitTo(WorkRecord, DateTime<Utc>),
+    /// restore the selected record from the trash
+    Restore(Vec<Trashed>),
+}
+
 #[derive(Debug)]
 pub struct App<'a> {
     pub title: &'a str,
+    pub profile: String,
     pub should_quit: bool,
 
     pub mode: Mode,
@@ -181,9 +439,15 @@ pub struct App<'a> {
     pub projects: StatefulList<&'a str>,
     pub active_project: Option<ActiveProject>,
     pub report: ReportState,
+    pub history: HistoryState,
+    pub prompt: Option<Prompt>,
 
     config: AppConfig,
+    undo_stack: UndoStack,
     auto_break: bool,
+    last_check: DateTime<Utc>,
+    last_heartbeat: DateTime<Utc>,
+    instance: InstanceLock,
 }
 
 fn string_to_static_string<'a>(s: String) -> &'a str {
@@ -191,29 +455,614 @@ fn string_to_static_string<'a>(s: String) -> &'a str {
 }
 
 impl<'a> App<'a> {
-    pub fn new(title: &'a str) -> App<'a> {
+    pub fn new(title: &'a str, instance: InstanceLock) -> App<'a> {
         let config = SETTINGS
             .read()
             .expect("could not acquire read lock on app settings");
         let config = config.deref();
-        let projects: Vec<&'a str> = config
-            .projects
-            .iter()
-            .map(|p| p.name.clone())
-            .map(string_to_static_string)
-            .collect();
-        App {
+        let mut app = App {
             title,
+            profile: Paths::current().profile,
             config: config.clone(),
             should_quit: false,
-            projects: StatefulList::with_items(projects),
+            projects: App::project_list(config),
             focus: Focus::Projects,
             mode: Mode::Normal(NormalMode {}),
-            active_project: ActiveProject::load_previous(),
+            active_project: None,
             report: ReportState::default(),
+            history: HistoryState::default(),
+            prompt: None,
+            undo_stack: UndoStack::default(),
             auto_break: false,
             auto_switch: true,
+            last_check: Utc::now(),
+            last_heartbeat: Utc::now(),
+            instance,
+        };
+        app.recover_previous();
+        app.purge_trash();
+        app
+    }
+
+    fn project_list(config: &AppConfig) -> StatefulList<&'a str> {
+        let projects: Vec<&'a str> = config
+            .projects
+            .iter()
+            .map(|p| p.name.clone())
+            .map(string_to_static_string)
+            .collect();
+        StatefulList::with_items(projects)
+    }
+
+    pub fn choose_profile(&mut self) {
+        let profiles = Paths::profiles();
+        let mut state = PromptState::choice("switch profile", profiles.clone());
+        if let PromptState::Choice { selection, .. } = &mut state {
+            selection.select(profiles.iter().position(|p| *p == self.profile));
+        }
+        self.open_prompt(state, PromptAction::SwitchProfile);
+    }
+
+    /// Stops the active record and continues with the config and records of another profile.
+    pub fn switch_profile(&mut self, profile: &str) {
+        if profile == self.profile {
+            return;
+        }
+        let paths = match Paths::current().of_other_profile(profile) {
+            Ok(paths) => paths,
+            Err(e) => {
+                log!("⚠ {}", e);
+                return;
+            }
+        };
+        let config = match AppConfig::load(&paths.config_file) {
+            Ok(config) => config,
+            Err(e) => {
+                log!("⚠ failed to load the config of profile {}: {}", profile, e);
+                return;
+            }
+        };
+        let instance = match lock_instance(&paths) {
+            Ok(instance) => instance,
+            Err(e) => {
+                log!("⚠ failed to switch to profile {}: {}", profile, e);
+                return;
+            }
+        };
+        let repository = match work_record::open(&paths.data_dir, &config.storage) {
+            Ok(repository) => repository,
+            Err(e) => {
+                log!("⚠ failed to open the records of profile {}: {}", profile, e);
+                return;
+            }
+        };
+
+        // the active record is stopped while the previous profile is still in place
+        self.active_project = None;
+        *WORK_RECORD_REPO.lock().unwrap() = repository;
+        *SETTINGS
+            .write()
+            .expect("could not acquire write lock on app settings") = config.clone();
+        Paths::set(paths);
+
+        self.instance = instance;
+        self.profile = profile.to_string();
+        self.projects = App::project_list(&config);
+        self.config = config;
+        self.report = ReportState::default();
+        self.history = HistoryState::default();
+        self.undo_stack = UndoStack::default();
+        self.auto_break = false;
+        log!("switched to profile {}", profile);
+        self.recover_previous();
+        self.purge_trash();
+    }
+
+    /// Picks up the latest record. Records that are still open but haven't seen a heartbeat in a
+    /// while were left behind by a crash, so the user has to decide how to close them.
+    fn recover_previous(&mut self) {
+        let Some(record) = ActiveProject::load_previous() else {
+            return;
+        };
+        let stale_after = chrono::Duration::seconds(2 * self.config.heartbeat.interval as i64);
+        if record.state == ProjectState::Done
+            || Utc::now().signed_duration_since(record.last_activity()) <= stale_after
+        {
+            self.active_project = Some(ActiveProject::from(record));
+            return;
+        }
+        log!(
+            "⚠ {} was not closed properly, last seen {}",
+            record.name,
+            format_local(&record.last_activity())
+        );
+        self.ask_recovery(record);
+    }
+
+    fn ask_recovery(&mut self, record: WorkRecord) {
+        let options = vec![
+            format!(
+                "close at last heartbeat ({})",
+                format_local(&record.last_activity())
+            ),
+            "keep it running".to_string(),
+            "close at a time I choose".to_string(),
+        ];
+        let title = format!("{} was not closed properly", record.name);
+        self.open_prompt(
+            PromptState::choice(&title, options),
+            PromptAction::Recover(record),
+        );
+    }
+
+    /// Asks why the active record is paused, unless there are no reasons to choose from.
+    pub fn ask_pause(&mut self) {
+        let Some(ref mut active_project) = self.active_project else {
+            return;
+        };
+        if active_project.record.state != Working || self.config.breaks.reasons.is_empty() {
+            // logs why a record that isn't worked on can't be paused
+            active_project.begin_pause(None);
+            return;
+        }
+        let mut options = vec![NO_REASON.to_string()];
+        options.extend(self.config.breaks.reasons.iter().cloned());
+        self.open_prompt(
+            PromptState::choice("pause for", options),
+            PromptAction::Pause(Utc::now()),
+        );
+    }
+
+    /// Asks which kind of time the active record continues with. Pauses have their own key.
+    pub fn choose_kind(&mut self) {
+        if self.active_project.is_none() {
+            log!("⚠ there is no record to switch");
+            return;
+        }
+        let mut kinds = vec![TimeKind::Productive.name().to_string()];
+        for kind in &self.config.kinds {
+            let kind = TimeKind::from(kind.name.clone());
+            if kind != TimeKind::Pause && !kinds.iter().any(|name| name == kind.name()) {
+                kinds.push(kind.name().to_string());
+            }
+        }
+        self.open_prompt(
+            PromptState::choice("continue with", kinds),
+            PromptAction::SwitchKind,
+        );
+    }
+
+    /// Asks for a new value of `field` of the active record.
+    pub fn edit_active(&mut self, field: RecordField) {
+        let Some(ref active_project) = self.active_project else {
+            log!("⚠ there is no record to edit");
+            return;
+        };
+        let record = active_project.record.clone();
+        self.ask_edit(record, field);
+    }
+
+    /// Asks which record of the report to edit.
+    fn choose_record(&mut self) {
+        let mut records = self.report.records.clone();
+        if records.is_empty() {
+            log!("⚠ there are no records in the report to edit");
+            return;
         }
+        records.sort_by_key(|record| record.start);
+        let options = records
+            .iter()
+            .map(|record| describe(record).to_string())
+            .collect();
+        self.open_prompt(
+            PromptState::choice("edit", options),
+            PromptAction::ChooseRecord(records),
+        );
+    }
+
+    fn ask_edit(&mut self, record: WorkRecord, field: RecordField) {
+        let title = format!(
+            "{} of {} at {}",
+            field.name(),
+            record.name,
+            format_local(&record.start)
+        );
+        let value = field.value(&record);
+        let state = match field {
+            RecordField::Project => {
+                let projects = self.config.projects.iter().map(|p| p.name.clone());
+                PromptState::choice_selecting(&title, projects.collect(), &value)
+            }
+            RecordField::State => {
+                let states = ["Working", "Paused", "Done"].map(str::to_string).to_vec();
+                PromptState::choice_selecting(&title, states, &value)
+            }
+            _ => PromptState::text(&title, &value),
+        };
+        self.open_prompt(state, PromptAction::Edit(record, field));
+    }
+
+    /// Saves `field` of `record` set to `input`, unless that leaves the record inconsistent or
+    /// overlapping another one. The active record is changed in place, so it doesn't overwrite
+    /// the change the next time it is saved.
+    fn set_field(
+        &mut self,
+        record: WorkRecord,
+        field: RecordField,
+        input: &str,
+    ) -> Result<(), String> {
+        let before = self.current_version(record);
+        let mut record = before.clone();
+        field.set(&mut record, input)?;
+        // records that overlapped before can still be described
+        if field.changes_times() {
+            self.validate(&record, None)?;
+        }
+        if self.continue_active(&[&record.id], &record) {
+            self.active_project.as_ref().unwrap().save();
+        } else if let Err(e) = WORK_RECORD_REPO.lock().unwrap().persist(record.clone()) {
+            log!("⚠ failed to save work record for {}: {}", record.name, e);
+        }
+        self.undo_stack.changed(Some(&before), Some(&record));
+        let value = field.value(&record);
+        log!(
+            "✎ {} of {}: {}",
+            field.name(),
+            record.name,
+            if value.is_empty() { "none" } else { &value }
+        );
+        self.refresh();
+        Ok(())
+    }
+
+    /// The active record may have changed since `record` was loaded, e.g. while a prompt was open.
+    fn current_version(&self, record: WorkRecord) -> WorkRecord {
+        match self.active_project {
+            Some(ref active_project) if active_project.record.id == record.id => {
+                active_project.record.clone()
+            }
+            _ => record,
+        }
+    }
+
+    /// Continues the active record with `record` if it is one of the records with `ids`, which
+    /// were replaced by it. Returns whether it did.
+    fn continue_active(&mut self, ids: &[&str], record: &WorkRecord) -> bool {
+        match self.active_project {
+            Some(ref mut active_project) if ids.contains(&active_project.record.id.as_str()) => {
+                active_project.record = record.clone();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Asks where to split the record selected in the history.
+    fn ask_split(&mut self) {
+        let Some(record) = self.history.selected().cloned() else {
+            log!("⚠ there is no record to split on {}", self.history.day);
+            return;
+        };
+        self.open_prompt(
+            PromptState::text(&format!("split {} at (HH:MM)", record.name), ""),
+            PromptAction::SplitAt(record),
+        );
+    }
+
+    /// Splits `record` at `at`, the rest of it goes to `project`. The active record continues
+    /// with the second part.
+    fn split_record(
+        &mut self,
+        record: WorkRecord,
+        at: DateTime<Utc>,
+        project: &str,
+    ) -> Result<(), String> {
+        let record = self.current_version(record);
+        let (first, second) = WORK_RECORD_REPO
+            .lock()
+            .unwrap()
+            .split(&record, at, Some(project))
+            .map_err(|e| e.to_string())?;
+        self.undo_stack.changed(Some(&record), Some(&first));
+        self.undo_stack.changed(None, Some(&second));
+        self.continue_active(&[&record.id], &second);
+        log!(
+            "✂ {} until {}, {} from then on",
+            first.name,
+            format_local(&at),
+            second.name
+        );
+        self.refresh();
+        Ok(())
+    }
+
+    /// Merges the record selected in the history with the one after it.
+    fn merge_selected(&mut self) {
+        self.undoable("merge", App::merge_records);
+    }
+
+    fn merge_records(&mut self) {
+        let Some(index) = self.history.selection.selected() else {
+            return;
+        };
+        let (Some(record), Some(next)) = (
+            self.history.records.get(index).cloned(),
+            self.history.records.get(index + 1).cloned(),
+        ) else {
+            log!("⚠ there is no record after the selected one to merge with");
+            return;
+        };
+        let (record, next) = (self.current_version(record), self.current_version(next));
+        let merged = WORK_RECORD_REPO.lock().unwrap().merge(&record, &next);
+        match merged {
+            Ok(merged) => {
+                self.undo_stack.changed(Some(&record), Some(&merged));
+                self.undo_stack.changed(Some(&next), None);
+                self.continue_active(&[&record.id, &next.id], &merged);
+                log!("⛙ merged {}", describe(&merged));
+                self.refresh();
+            }
+            Err(e) => log!("⚠ {}", e),
+        }
+    }
+
+    /// Checks `record` against the stored records it may overlap, see [history::validate].
+    /// `changed` is about to be saved as well and replaces its stored version.
+    fn validate(&self, record: &WorkRecord, changed: Option<&WorkRecord>) -> Result<(), String> {
+        let end = record
+            .end
+            .unwrap_or_else(|| record.last_activity().max(Utc::now()));
+        let mut others = WORK_RECORD_REPO
+            .lock()
+            .unwrap()
+            .find_range(record.start, end, None)
+            .map_err(|e| e.to_string())?;
+        if let Some(changed) = changed {
+            others.retain(|other| other.id != changed.id);
+            others.push(changed.clone());
+        }
+        history::validate(record, &others).map_err(|problem| problem.to_string())
+    }
+
+    /// Asks which field of the record selected in the history to edit.
+    fn choose_field(&mut self) {
+        let Some(record) = self.history.selected().cloned() else {
+            log!("⚠ there is no record to edit on {}", self.history.day);
+            return;
+        };
+        self.ask_field(record);
+    }
+
+    /// Asks which field of `record` to edit.
+    fn ask_field(&mut self, record: WorkRecord) {
+        let fields = RecordField::ALL.map(|field| field.name().to_string());
+        self.open_prompt(
+            PromptState::choice(&format!("edit {}", describe(&record)), fields.to_vec()),
+            PromptAction::ChooseField(record),
+        );
+    }
+
+    /// Asks for the project of a record for work that wasn't tracked.
+    fn ask_new_record(&mut self) {
+        let projects = self.config.projects.iter().map(|p| p.name.clone());
+        self.open_prompt(
+            PromptState::choice(
+                &format!("add a record on {}", self.history.day),
+                projects.collect(),
+            ),
+            PromptAction::NewRecord,
+        );
+    }
+
+    /// Adds a record of `project` from and to the times in `input` on the day of the history.
+    fn add_record(&mut self, project: &str, input: &str) -> Result<(), String> {
+        let day = self.history.day;
+        let times: Vec<_> = input
+            .split_whitespace()
+            .map(|time| parse_local(time, day))
+            .collect();
+        let (start, end) = match times[..] {
+            [Some(start), Some(end)] if start < end && end <= Utc::now() => (start, end),
+            _ => return Err(format!("'{input}' is not a valid range of times until now")),
+        };
+        let tags = self.config.project_tags(project);
+        let record = history::manual_record(project.to_string(), tags, start, end);
+        self.validate(&record, None)?;
+        WORK_RECORD_REPO
+            .lock()
+            .unwrap()
+            .persist(record.clone())
+            .map_err(|e| e.to_string())?;
+        self.undo_stack.changed(None, Some(&record));
+        log!("✚ {}", describe(&record));
+        self.refresh();
+        Ok(())
+    }
+
+    /// Moves the record selected in the history to the trash. The active record has to be
+    /// stopped first.
+    fn trash_selected(&mut self) {
+        let Some(record) = self.history.selected().cloned() else {
+            log!("⚠ there is no record to delete on {}", self.history.day);
+            return;
+        };
+        if let Some(ref active_project) = self.active_project {
+            if active_project.record.id == record.id && record.state != ProjectState::Done {
+                log!("⚠ stop {} before deleting it", record.name);
+                return;
+            }
+        }
+        self.undoable("delete", |app| {
+            if let Err(e) = app.trash(&record) {
+                log!("⚠ failed to delete {}: {}", record.name, e);
+                return;
+            }
+            app.undo_stack.changed(Some(&record), None);
+            if app.active_project.as_ref().map(|active| &active.record.id) == Some(&record.id) {
+                // it is done already, so nothing is saved when it is dropped
+                app.active_project = None;
+            }
+            log!("🗑 deleted {}", describe(&record));
+            app.refresh();
+        });
+    }
+
+    /// Puts `record` in the trash before deleting it from the database, so it is never lost.
+    fn trash(&self, record: &WorkRecord) -> error::Result<()> {
+        Trash::new(&Paths::current().data_dir).put(record.clone(), Utc::now())?;
+        WORK_RECORD_REPO.lock().unwrap().delete(record)
+    }
+
+    /// Asks which record of the trash to restore.
+    fn ask_restore(&mut self) {
+        let entries = match Trash::new(&Paths::current().data_dir).list() {
+            Ok(entries) => entries,
+            Err(e) => {
+                log!("⚠ failed to read the trash: {}", e);
+                return;
+            }
+        };
+        if entries.is_empty() {
+            log!("⚠ the trash is empty");
+            return;
+        }
+        let options = entries
+            .iter()
+            .map(|entry| {
+                format!(
+                    "{} (deleted {})",
+                    describe(&entry.record),
+                    format_local(&entry.deleted)
+                )
+            })
+            .collect();
+        self.open_prompt(
+            PromptState::choice("restore", options),
+            PromptAction::Restore(entries),
+        );
+    }
+
+    /// Saves `record` again and takes it out of the trash, unless it overlaps a record that was
+    /// added since it was deleted.
+    fn restore(&mut self, record: WorkRecord) -> Result<(), String> {
+        self.validate(&record, None)?;
+        WORK_RECORD_REPO
+            .lock()
+            .unwrap()
+            .persist(record.clone())
+            .map_err(|e| e.to_string())?;
+        if let Err(e) = Trash::new(&Paths::current().data_dir).take(&record.id) {
+            log!("⚠ failed to take {} out of the trash: {}", record.name, e);
+        }
+        self.undo_stack.changed(None, Some(&record));
+        log!("♻ restored {}", describe(&record));
+        self.history
+            .show_day(record.start.with_timezone(&Local).date_naive());
+        self.refresh();
+        Ok(())
+    }
+
+    /// Removes the records that have been in the trash longer than configured for good.
+    fn purge_trash(&self) {
+        let retention = chrono::Duration::days(self.config.trash.retention as i64);
+        match Trash::new(&Paths::current().data_dir).purge(retention, Utc::now()) {
+            Ok(0) => {}
+            Ok(removed) => log!("🗑 removed {} records from the trash for good", removed),
+            Err(e) => log!("⚠ failed to empty the trash: {}", e),
+        }
+    }
+
+    /// Shows the changes of a record in the report and the history, if they are open.
+    fn refresh(&mut self) {
+        if self.report.report.is_some() {
+            self.report.calculate();
+        }
+        if self.focus == Focus::History {
+            self.history.load();
+        }
+    }
+
+    /// Runs `operation` as one step that can be undone, together with the changes of the
+    /// operations it runs itself.
+    pub fn undoable(&mut self, name: impl Into<String>, operation: impl FnOnce(&mut Self)) {
+        if self.undo_stack.is_recording() {
+            operation(self);
+            return;
+        }
+        let active = self.active_project.as_ref().map(|project| &project.record);
+        self.undo_stack.begin(name.into(), active);
+        operation(self);
+        let active = self.active_project.as_ref().map(|project| &project.record);
+        self.undo_stack.commit(active);
+    }
+
+    /// Continues with `next`, the previous active record is stopped if it is still open.
+    fn replace_active(&mut self, next: ActiveProject) {
+        if let Some(ref previous) = self.active_project {
+            self.undo_stack
+                .changed(Some(&previous.record), Some(&previous.record));
+        }
+        self.active_project = Some(next);
+    }
+
+    pub fn undo(&mut self) {
+        match self.undo_stack.undo() {
+            Some(step) => {
+                self.apply(&step);
+                log!("↶ undid {}", step.name);
+                self.refresh();
+            }
+            None => log!("⚠ there is nothing to undo"),
+        }
+    }
+
+    pub fn redo(&mut self) {
+        match self.undo_stack.redo() {
+            Some(step) => {
+                self.apply(&step);
+                log!("↷ redid {}", step.name);
+                self.refresh();
+            }
+            None => log!("⚠ there is nothing to redo"),
+        }
+    }
+
+    /// Saves the records of `step` the way it left them and continues with its active record.
+    /// Records the step removes go to the trash, an active record it doesn't know about is
+    /// stopped.
+    fn apply(&mut self, step: &Step) {
+        let trash = Trash::new(&Paths::current().data_dir);
+        for change in &step.changes {
+            let result = match (&change.before, &change.after) {
+                (None, Some(after)) => trash
+                    .take(&after.id)
+                    .and_then(|_| WORK_RECORD_REPO.lock().unwrap().persist(after.clone())),
+                (Some(_), Some(after)) => WORK_RECORD_REPO.lock().unwrap().persist(after.clone()),
+                (Some(before), None) => self.trash(before),
+                (None, None) => Ok(()),
+            };
+            if let Err(e) = result {
+                log!("⚠ failed to restore {}: {}", step.name, e);
+            }
+        }
+        if let Some(mut current) = self.active_project.take() {
+            let id = Some(current.record.id.as_str());
+            current.keep_open = step
+                .changes
+                .iter()
+                .flat_map(|change| [&change.before, &change.after])
+                .chain([&step.active.1])
+                .any(|record| record.as_ref().map(|record| record.id.as_str()) == id);
+        }
+        self.active_project = step.active_after().cloned().map(ActiveProject::from);
+    }
+
+    fn close_recovered(&mut self, mut record: WorkRecord, end: DateTime<Utc>) {
+        record.close_at(end);
+        log!("{}", describe(&record));
+        let project = ActiveProject::from(record);
+        project.save();
+        self.active_project = Some(project);
     }
 
     pub fn on_up(&mut self) {
@@ -236,11 +1085,216 @@ impl<'a> App<'a> {
         self.set_mode(Mode::Filter(FilterMode {}));
     }
 
+    fn open_prompt(&mut self, state: PromptState, action: PromptAction) {
+        self.prompt = Some(Prompt { state, action });
+        self.set_mode(Mode::Prompt(PromptMode {}));
+    }
+
+    pub fn submit_prompt(&mut self) {
+        let Some(prompt) = self.prompt.take() else {
+            return;
+        };
+        self.normal_mode();
+        match prompt.action {
+            PromptAction::Recover(record) => match prompt.state.selected() {
+                Some(0) => {
+                    let end = record.last_activity();
+                    self.close_recovered(record, end);
+                }
+                Some(1) => {
+                    log!("♪ keeping {} running", record.name);
+                    self.active_project = Some(ActiveProject::from(record));
+                }
+                _ => self.open_prompt(
+                    PromptState::text("close at (HH:MM or YYYY-MM-DD HH:MM)", ""),
+                    PromptAction::RecoverAt(record),
+                ),
+            },
+            PromptAction::SwitchProfile => self.switch_profile(&prompt.state.value()),
+            PromptAction::Pause(at) => {
+                let reason = match prompt.state.selected() {
+                    Some(0) | None => None,
+                    Some(_) => Some(prompt.state.value()),
+                };
+                self.undoable("pause", |app| {
+                    if let Some(ref mut active_project) = app.active_project {
+                        active_project.pause_at(at, reason);
+                    }
+                });
+            }
+            PromptAction::SwitchKind => {
+                let kind = TimeKind::from(prompt.state.value());
+                self.undoable(format!("continue with {}", kind.name()), |app| {
+                    if let Some(ref mut active_project) = app.active_project {
+                        active_project.switch_kind(kind);
+                    }
+                });
+            }
+            PromptAction::ReportRange => {
+                let input = prompt.state.value();
+                let days: Vec<NaiveDate> = input
+                    .split_whitespace()
+                    .filter_map(|day| NaiveDate::parse_from_str(day, "%Y-%m-%d").ok())
+                    .collect();
+                match days[..] {
+                    [first, last] if first <= last => {
+                        self.report.picker.set_custom(first, last);
+                        self.report.calculate();
+                    }
+                    _ => log!("⚠ '{}' is not a valid range of days", input),
+                }
+            }
+            PromptAction::ChooseRecord(records) => {
+                if let Some(record) = prompt
+                    .state
+                    .selected()
+                    .and_then(|index| records.into_iter().nth(index))
+                {
+                    self.ask_field(record);
+                }
+            }
+            PromptAction::Edit(record, field) => {
+                let mut result = Ok(());
+                self.undoable(format!("edit {}", field.name()), |app| {
+                    result = app.set_field(record.clone(), field, &prompt.state.value());
+                });
+                if let Err(e) = result {
+                    log!("⚠ {} at {} {}", record.name, format_local(&record.start), e);
+                    self.open_prompt(prompt.state, PromptAction::Edit(record, field));
+                }
+            }
+            PromptAction::ChooseField(record) => {
+                if let Some(field) = prompt
+                    .state
+                    .selected()
+                    .and_then(|index| RecordField::ALL.get(index))
+                {
+                    self.ask_edit(record, *field);
+                }
+            }
+            PromptAction::NewRecord => self.open_prompt(
+                PromptState::text("from to (HH:MM HH:MM)", ""),
+                PromptAction::NewRecordAt(prompt.state.value()),
+            ),
+            PromptAction::SplitAt(record) => {
+                let input = prompt.state.value();
+                let day = record.start.with_timezone(&Local).date_naive();
+                match parse_local(&input, day) {
+                    Some(at) => {
+                        let projects = self.config.projects.iter().map(|p| p.name.clone());
+                        self.open_prompt(
+                            PromptState::choice_selecting(
+                                &format!("continue from {} with", input.trim()),
+                                projects.collect(),
+                                &record.name,
+                            ),
+                            PromptAction::SplitTo(record, at),
+                        )
+                    }
+                    None => {
+                        log!("⚠ '{}' is not a time (HH:MM)", input);
+                        self.open_prompt(prompt.state, PromptAction::SplitAt(record));
+                    }
+                }
+            }
+            PromptAction::StartAt(project) => {
+                let input = prompt.state.value();
+                let mut result = Ok(());
+                self.undoable(format!("start {project}"), |app| {
+                    result = parse_local(&input, Local::now().date_naive())
+                        .ok_or_else(|| format!("'{}' is not a time (HH:MM)", input.trim()))
+                        .and_then(|at| app.start_working_on_at(&project, at));
+                });
+                if let Err(e) = result {
+                    log!("⚠ {} can't be started: {}", project, e);
+                    self.open_prompt(prompt.state, PromptAction::StartAt(project));
+                }
+            }
+            PromptAction::SplitTo(record, at) => {
+                let mut result = Ok(());
+                self.undoable("split", |app| {
+                    result = app.split_record(record, at, &prompt.state.value());
+                });
+                if let Err(e) = result {
+                    log!("⚠ {}", e);
+                }
+            }
+            PromptAction::Restore(entries) => {
+                if let Some(entry) = prompt
+                    .state
+                    .selected()
+                    .and_then(|index| entries.into_iter().nth(index))
+                {
+                    let mut result = Ok(());
+                    self.undoable("restore", |app| result = app.restore(entry.record));
+                    if let Err(e) = result {
+                        log!("⚠ {}", e);
+                    }
+                }
+            }
+            PromptAction::NewRecordAt(project) => {
+                let mut result = Ok(());
+                self.undoable("add record", |app| {
+                    result = app.add_record(&project, &prompt.state.value());
+                });
+                if let Err(e) = result {
+                    log!("⚠ {} can't be added: {}", project, e);
+                    self.open_prompt(prompt.state, PromptAction::NewRecordAt(project));
+                }
+            }
+            PromptAction::RecoverAt(record) => {
+                let input = prompt.state.value();
+                let day = record.last_activity().with_timezone(&Local).date_naive();
+                match parse_local(&input, day) {
+                    Some(end) if end > record.start && end <= Utc::now() => {
+                        self.close_recovered(record, end)
+                    }
+                    _ => {
+                        log!(
+                            "⚠ '{}' is not a valid time between the start of {} and now",
+                            input,
+                            record.name
+                        );
+                        self.open_prompt(prompt.state, PromptAction::RecoverAt(record));
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn cancel_prompt(&mut self) {
+        let Some(prompt) = self.prompt.take() else {
+            return;
+        };
+        self.normal_mode();
+        match prompt.action {
+            // a crashed record has to be dealt with, so there is no way around this prompt
+            PromptAction::Recover(record) => {
+                self.open_prompt(prompt.state, PromptAction::Recover(record))
+            }
+            PromptAction::RecoverAt(record) => self.ask_recovery(record),
+            PromptAction::SwitchProfile
+            | PromptAction::Pause(_)
+            | PromptAction::SwitchKind
+            | PromptAction::ReportRange
+            | PromptAction::ChooseRecord(..)
+            | PromptAction::Edit(..)
+            | PromptAction::ChooseField(_)
+            | PromptAction::NewRecord
+            | PromptAction::NewRecordAt(_)
+            | PromptAction::SplitAt(_)
+            | PromptAction::StartAt(_)
+            | PromptAction::SplitTo(..)
+            | PromptAction::Restore(_) => {}
+        }
+    }
+
     fn focus_next(&mut self) {
         self.focus = match self.focus {
             Focus::Projects => Focus::Log,
             Focus::Log => Focus::Projects,
             Focus::Report => Focus::Report,
+            Focus::History => Focus::History,
         };
     }
 
@@ -249,24 +1303,74 @@ impl<'a> App<'a> {
             Focus::Projects => Focus::Log,
             Focus::Log => Focus::Projects,
             Focus::Report => Focus::Report,
+            Focus::History => Focus::History,
         };
     }
 
     pub fn start_working_on(&mut self, project: String) {
+        let now = Utc::now();
         if let Some(ref mut current_project) = self.active_project {
             if current_project.record.name == project {
-                current_project.resume_work();
-                return;
+                // switching back to the window of the project that is worked on changes nothing
+                if current_project.record.state == ProjectState::Paused {
+                    current_project.resume_work();
+                }
+                if current_project.record.state != ProjectState::Done {
+                    return;
+                }
+            }
+            current_project.stop_at(now);
+        }
+        let tags = self.config.project_tags(&project);
+        self.replace_active(ActiveProject::start(WorkRecord::new(project, tags, now)));
+    }
+
+    /// Asks since when the selected project has been worked on, for when switching was forgotten.
+    pub fn ask_start_at(&mut self) {
+        let Some(project) = self.projects.get_selected() else {
+            return;
+        };
+        let project = project.to_string();
+        self.open_prompt(
+            PromptState::text(&format!("working on {project} since (HH:MM)"), ""),
+            PromptAction::StartAt(project),
+        );
+    }
+
+    /// Starts `project` at `at` in the past, the active record is cut off there.
+    fn start_working_on_at(&mut self, project: &str, at: DateTime<Utc>) -> Result<(), String> {
+        let latest = match self.active_project {
+            Some(ref active_project) => Some(active_project.record.clone()),
+            None => ActiveProject::load_previous(),
+        };
+        let tags = self.config.project_tags(project);
+        let (cut, record) = history::start_at(latest.as_ref(), project, tags, at, Utc::now())?;
+        self.validate(&record, cut.as_ref())?;
+        if let Some(cut) = cut {
+            if self.continue_active(&[&cut.id], &cut) {
+                self.active_project.as_ref().unwrap().save();
+            } else if let Err(e) = WORK_RECORD_REPO.lock().unwrap().persist(cut.clone()) {
+                return Err(format!("failed to save {}: {}", cut.name, e));
             }
-            current_project.stop();
+            self.undo_stack.changed(latest.as_ref(), Some(&cut));
+            log!("{}", describe(&cut));
         }
-        self.active_project = Some(ActiveProject::new(project));
+        self.replace_active(ActiveProject::start(record));
+        self.refresh();
+        Ok(())
     }
 
     pub fn on_input(&mut self, event: KeyEvent) {
+        if let Mode::Prompt(mode) = self.mode {
+            mode.on_input(event, self);
+            return;
+        }
         if self.focus == Focus::Report && self.on_report_input(event) {
             return;
         }
+        if self.focus == Focus::History && self.on_history_input(event) {
+            return;
+        }
         match (self.mode, event.code, event.kind) {
             (Mode::Normal(_), KeyCode::Char('/'), KeyEventKind::Release) => self.filter_mode(),
             (Mode::Normal(_), KeyCode::Char('q'), KeyEventKind::Release) => self.should_quit = true,
@@ -277,6 +1381,14 @@ impl<'a> App<'a> {
                     Focus::Report
                 }
             }
+            (Mode::Normal(_), KeyCode::Char('h'), KeyEventKind::Release) => {
+                self.focus = if self.focus == Focus::History {
+                    Focus::Projects
+                } else {
+                    self.history.load();
+                    Focus::History
+                }
+            }
             (Mode::Filter(_), KeyCode::Enter | KeyCode::Esc, KeyEventKind::Release) => {
                 self.normal_mode()
             }
@@ -294,6 +1406,7 @@ impl<'a> App<'a> {
 
             (Mode::Normal(ref mode), _, _) => mode.on_input(event, self),
             (Mode::Filter(ref mode), _, _) => mode.on_input(event, self),
+            (Mode::Prompt(ref mode), _, _) => mode.on_input(event, self),
         }
     }
 
@@ -301,10 +1414,34 @@ impl<'a> App<'a> {
         let mut handled = true;
         match (event.code, event.kind) {
             (KeyCode::Left, KeyEventKind::Press | KeyEventKind::Repeat) => {
-                self.report.weekpicker.decrement()
+                self.report.picker.decrement()
             }
             (KeyCode::Right, KeyEventKind::Press | KeyEventKind::Repeat) => {
-                self.report.weekpicker.increment()
+                self.report.picker.increment()
+            }
+            (KeyCode::Char('g'), KeyEventKind::Press) => self.report.picker.next_period(),
+            (KeyCode::Char('c'), KeyEventKind::Press) => {
+                let (start, end) = self.report.picker.start_and_end();
+                self.open_prompt(
+                    PromptState::text(
+                        "report from to (YYYY-MM-DD YYYY-MM-DD)",
+                        &format!("{start} {end}"),
+                    ),
+                    PromptAction::ReportRange,
+                )
+            }
+            (KeyCode::Char('v'), KeyEventKind::Press) => {
+                self.report.details = !self.report.details;
+                self.report.calculate();
+            }
+            (KeyCode::Char('e'), KeyEventKind::Press) => self.choose_record(),
+            (KeyCode::Char('T'), KeyEventKind::Press) => {
+                self.report.by_tag = !self.report.by_tag;
+                self.report.calculate();
+            }
+            (KeyCode::Char('B'), KeyEventKind::Press) => {
+                self.report.breaks = !self.report.breaks;
+                self.report.calculate();
             }
             (KeyCode::Enter, KeyEventKind::Release) => self.report.calculate(),
             (KeyCode::Esc, KeyEventKind::Release) => self.focus = Focus::Projects,
@@ -314,6 +1451,34 @@ impl<'a> App<'a> {
         handled
     }
 
+    fn on_history_input(&mut self, event: KeyEvent) -> bool {
+        let mut handled = true;
+        match (event.code, event.kind) {
+            (KeyCode::Left, KeyEventKind::Press | KeyEventKind::Repeat) => {
+                self.history.show_day(self.history.day - Days::new(1))
+            }
+            (KeyCode::Right, KeyEventKind::Press | KeyEventKind::Repeat) => {
+                self.history.show_day(self.history.day + Days::new(1))
+            }
+            (KeyCode::Up, KeyEventKind::Press | KeyEventKind::Repeat) => {
+                self.history.select_next(-1)
+            }
+            (KeyCode::Down, KeyEventKind::Press | KeyEventKind::Repeat) => {
+                self.history.select_next(1)
+            }
+            (KeyCode::Char('e') | KeyCode::Enter, KeyEventKind::Press) => self.choose_field(),
+            (KeyCode::Char('n'), KeyEventKind::Press) => self.ask_new_record(),
+            (KeyCode::Char('S'), KeyEventKind::Press) => self.ask_split(),
+            (KeyCode::Char('m'), KeyEventKind::Press) => self.merge_selected(),
+            (KeyCode::Char('D') | KeyCode::Delete, KeyEventKind::Press) => self.trash_selected(),
+            (KeyCode::Char('R'), KeyEventKind::Press) => self.ask_restore(),
+            (KeyCode::Esc, KeyEventKind::Release) => self.focus = Focus::Projects,
+
+            _ => handled = false,
+        }
+        handled
+    }
+
     pub(crate) fn on_window_focus_changed(&mut self, window_title: &String) {
         if !self.auto_switch {
             return;
@@ -321,6 +1486,11 @@ impl<'a> App<'a> {
         if self.config.logging.window_change {
             log!("title changed: {}", window_title)
         }
+        self.undoable("auto-switch", |app| app.follow_window(window_title));
+    }
+
+    /// Starts, pauses, resumes or tags the active record depending on the focused window.
+    fn follow_window(&mut self, window_title: &str) {
         // if we previously went on auto break and auto resume is configured, resume
         if self.auto_break && self.config.breaks.auto_resume {
             if let Some(ref mut active_project) = self.active_project {
@@ -344,8 +1514,10 @@ impl<'a> App<'a> {
         // if a project was found, start work on that project
         if let Some(project) = associated_project {
             self.start_working_on(project);
+            self.tag_by_window(window_title);
             return;
         }
+        self.tag_by_window(window_title);
 
         // check if the window is configured to trigger an automatic break (i.e. lockscreens)
         let go_on_break = self.config.breaks.windows.iter().any(|title| {
@@ -356,14 +1528,104 @@ impl<'a> App<'a> {
         if go_on_break {
             if let Some(ref mut active_project) = self.active_project {
                 // start the break and set auto_break, so we can auto resume if configured
-                active_project.begin_pause();
+                active_project.begin_pause(Some(LOCK_SCREEN.to_string()));
                 self.auto_break = true;
             }
         }
     }
 
+    /// Tags the active record with every tag configured for the window.
+    fn tag_by_window(&mut self, window_title: &str) {
+        let Some(ref mut active_project) = self.active_project else {
+            return;
+        };
+        let window_title = window_title.to_lowercase();
+        let tags: Vec<String> = self
+            .config
+            .tags
+            .iter()
+            .filter(|rule| {
+                rule.windows
+                    .iter()
+                    .any(|window| window_title.starts_with(&window.to_lowercase()))
+            })
+            .map(|rule| rule.name.clone())
+            .collect();
+        if active_project.record.state != ProjectState::Done {
+            active_project.add_tags(&tags);
+        }
+    }
+
+    /// Looks for a suspend or clock jump since the last check. Runs before any event is handled,
+    /// so the first key or window change after a resume already sees the pause.
+    pub fn check_clock(&mut self) {
+        let now = Utc::now();
+        let previous_check = self.last_check;
+        self.last_check = now;
+
+        let elapsed = now.signed_duration_since(previous_check);
+        let threshold = chrono::Duration::seconds(self.config.suspend.gap_threshold as i64);
+        if elapsed < chrono::Duration::zero() {
+            log!(
+                "⚠ system clock jumped back by {}s, keeping the active record as is",
+                -elapsed.num_seconds()
+            );
+        } else if elapsed > threshold {
+            self.on_time_gap(previous_check, now);
+        }
+    }
+
     pub fn on_tick(&mut self) {
-        // Update progress
+        let now = Utc::now();
+        if let Some(ref mut active_project) = self.active_project {
+            active_project.roll_over(now);
+        }
+
+        let since_heartbeat = now.signed_duration_since(self.last_heartbeat);
+        let interval = chrono::Duration::seconds(self.config.heartbeat.interval as i64);
+        if since_heartbeat >= interval || since_heartbeat < chrono::Duration::zero() {
+            self.last_heartbeat = now;
+            if let Some(ref mut active_project) = self.active_project {
+                active_project.heartbeat(now);
+            }
+        }
+    }
+
+    /// The wall clock advanced way more than one tick, so either the system was suspended or the
+    /// clock jumped. Nobody was working in between, so the gap is booked as a pause.
+    fn on_time_gap(&mut self, from: DateTime<Utc>, to: DateTime<Utc>) {
+        let gap = to.signed_duration_since(from);
+        log!(
+            "⏾ detected a gap of {:02}:{:02}:{:02} (suspend or clock jump)",
+            gap.num_hours(),
+            gap.num_minutes() % 60,
+            gap.num_seconds() % 60
+        );
+        if let Some(ref mut active_project) = self.active_project {
+            if active_project.record.state == Working {
+                active_project.insert_pause(from, to);
+                log!(
+                    "𝄽 inserted a pause from {} to {}",
+                    from.with_timezone(chrono::Local::now().offset())
+                        .format("%Y-%m-%d %H:%M"),
+                    to.with_timezone(chrono::Local::now().offset())
+                        .format("%Y-%m-%d %H:%M")
+                );
+            }
+        }
+    }
+
+    /// The process is being terminated from the outside, so the active record has to be saved
+    /// right away instead of relying on it being dropped.
+    pub fn on_termination(&mut self) {
+        let Some(ref mut active_project) = self.active_project else {
+            return;
+        };
+        match self.config.shutdown.on_signal {
+            ShutdownAction::Stop => active_project.stop(),
+            ShutdownAction::Pause => active_project.pause_and_keep_open(),
+        }
+        log!("terminated, saved {}", active_project);
     }
 
     #[allow(dead_code)]
@@ -372,6 +1634,7 @@ impl<'a> App<'a> {
             Focus::Projects => Some(&mut self.projects),
             Focus::Log => None,
             Focus::Report => None,
+            Focus::History => None,
         }
     }
 
@@ -381,3 +1644,189 @@ impl<'a> App<'a> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::MutexGuard;
+
+    use crossterm::event::KeyModifiers;
+
+    use tempfile::TempDir;
+
+    use crate::repository::json::JsonRepository;
+
+    use super::*;
+
+    /// the app works on the global repository, settings and paths, so tests take turns
+    static GLOBALS: Mutex<()> = Mutex::new(());
+
+    struct TestApp {
+        app: App<'static>,
+        directory: TempDir,
+        _globals: MutexGuard<'static, ()>,
+    }
+
+    /// An app on a new data directory containing `records`, which are written to the week files.
+    fn app_with(records: &[WorkRecord]) -> TestApp {
+        let globals = GLOBALS.lock().unwrap_or_else(|e| e.into_inner());
+        let directory = tempfile::tempdir().unwrap();
+        let mut repository = JsonRepository::new(directory.path()).unwrap();
+        for record in records {
+            repository.persist(record.clone()).unwrap();
+        }
+        repository.compact().unwrap();
+
+        let config_file = directory.path().join("config.yml");
+        fs::write(
+            &config_file,
+            "projects:\n  - name: EKS\n  - name: Swag\nclients: []\n",
+        )
+        .unwrap();
+        let mut paths = Paths::current();
+        paths.config_file = config_file.clone();
+        paths.data_dir = directory.path().to_path_buf();
+        Paths::set(paths);
+        let config = AppConfig::load(&config_file).unwrap();
+        *WORK_RECORD_REPO.lock().unwrap() =
+            work_record::open(directory.path(), &config.storage).unwrap();
+        *SETTINGS.write().unwrap() = config;
+
+        let instance = InstanceLock::acquire(directory.path()).unwrap();
+        let mut app = App::new("test", instance);
+        app.focus = Focus::History;
+        if let Some(record) = records.first() {
+            app.history
+                .show_day(record.start.with_timezone(&Local).date_naive());
+        }
+        TestApp {
+            app,
+            directory,
+            _globals: globals,
+        }
+    }
+
+    fn record(name: &str, from: &str, to: &str) -> WorkRecord {
+        let day = NaiveDate::from_ymd_opt(2023, 7, 4).unwrap();
+        let start = parse_local(from, day).unwrap();
+        let mut record = WorkRecord::new(name.to_string(), Default::default(), start);
+        record.close_at(parse_local(to, day).unwrap());
+        record
+    }
+
+    fn stored(record: &WorkRecord) -> Vec<WorkRecord> {
+        let mut records = WORK_RECORD_REPO
+            .lock()
+            .unwrap()
+            .find_week(&record.start)
+            .unwrap();
+        records.sort_by_key(|record| record.start);
+        records
+    }
+
+    #[test]
+    fn test_restore() {
+        let record = record("EKS", "08:00", "09:00");
+        let mut test = app_with(std::slice::from_ref(&record));
+
+        test.app.trash_selected();
+        assert!(stored(&record).is_empty());
+        test.app.restore(record.clone()).unwrap();
+
+        assert_eq!(stored(&record), vec![record]);
+        let trash = Trash::new(test.directory.path()).list().unwrap();
+        assert!(trash.is_empty());
+    }
+
+    #[test]
+    fn test_recover_stale_paused_record() {
+        let start = Utc::now() - chrono::Duration::hours(2);
+        let mut record = WorkRecord::new("EKS".to_string(), Default::default(), start);
+        record
+            .pause_at(start + chrono::Duration::hours(1), None)
+            .unwrap();
+        record.last_seen = Some(start + chrono::Duration::hours(1));
+        let test = app_with(&[record]);
+
+        assert!(test.app.active_project.is_none());
+        assert!(matches!(
+            test.app.prompt,
+            Some(Prompt {
+                action: PromptAction::Recover(_),
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_main_actions_in_history() {
+        let start = Utc::now() - chrono::Duration::minutes(10);
+        let mut record = WorkRecord::new("EKS".to_string(), Default::default(), start);
+        record
+            .pause_at(start + chrono::Duration::minutes(5), None)
+            .unwrap();
+        record.last_seen = Some(Utc::now());
+        let mut test = app_with(&[record]);
+        let press = |key| KeyEvent::new(KeyCode::Char(key), KeyModifiers::NONE);
+
+        test.app.on_input(press('r'));
+        assert!(test.app.prompt.is_none());
+        let active = &test.app.active_project.as_ref().unwrap().record;
+        assert_eq!(active.state, ProjectState::Working);
+        test.app.on_input(press('s'));
+        assert!(test.app.prompt.is_none());
+        let active = &test.app.active_project.as_ref().unwrap().record;
+        assert_eq!(active.state, ProjectState::Done);
+    }
+
+    #[test]
+    fn test_split_active() {
+        let start = Utc::now() - chrono::Duration::minutes(10);
+        let mut record = WorkRecord::new("EKS".to_string(), Default::default(), start);
+        record.last_seen = Some(Utc::now());
+        let mut test = app_with(std::slice::from_ref(&record));
+        assert!(test.app.active_project.is_some());
+
+        let at = start + chrono::Duration::minutes(5);
+        test.app.split_record(record.clone(), at, "Swag").unwrap();
+        let active = &test.app.active_project.as_ref().unwrap().record;
+        assert_eq!(active.name, "Swag");
+        assert_eq!(active.start, at);
+        test.app.on_termination();
+
+        let records = stored(&record);
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].id, record.id);
+        assert_eq!(records[0].end, Some(at));
+        assert_eq!(records[1].name, "Swag");
+    }
+
+    #[test]
+    fn test_undo_delete() {
+        let record = record("EKS", "08:00", "09:00");
+        let mut test = app_with(std::slice::from_ref(&record));
+
+        test.app.trash_selected();
+        test.app.undo();
+        assert_eq!(stored(&record), vec![record.clone()]);
+        assert!(Trash::new(test.directory.path()).list().unwrap().is_empty());
+
+        test.app
+            .on_input(KeyEvent::new(KeyCode::Char('r'), KeyModifiers::CONTROL));
+        assert!(test.app.prompt.is_none());
+        assert!(stored(&record).is_empty());
+    }
+
+    #[test]
+    fn test_undo_merge() {
+        let first = record("EKS", "08:00", "09:00");
+        let second = record("EKS", "09:00", "10:00");
+        let mut test = app_with(&[first.clone(), second.clone()]);
+        test.app.history.selection.select(Some(0));
+
+        test.app.merge_selected();
+        assert_eq!(stored(&first).len(), 1);
+        test.app.undo();
+
+        assert_eq!(stored(&first), vec![first, second]);
+    }
+}
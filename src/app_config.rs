@@ -1,7 +1,11 @@
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
+use std::path::Path;
 
+use config::{Config, ConfigError};
 use serde::{Deserialize, Serialize};
 
+use crate::repository::model::TimeKind;
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct AppConfig {
     pub projects: Vec<ProjectConfig>,
@@ -10,6 +14,63 @@ pub struct AppConfig {
     pub logging: LoggingConfig,
     #[serde(default)]
     pub breaks: BreakConfig,
+    #[serde(default)]
+    pub suspend: SuspendConfig,
+    #[serde(default)]
+    pub heartbeat: HeartbeatConfig,
+    #[serde(default)]
+    pub shutdown: ShutdownConfig,
+    #[serde(default)]
+    pub storage: StorageConfig,
+    /// tags added to the active record while one of their windows has the focus
+    #[serde(default)]
+    pub tags: Vec<TagConfig>,
+    /// kinds of time besides `Productive` and `Pause`, which may also be configured here
+    #[serde(default)]
+    pub kinds: Vec<TimeKindConfig>,
+    #[serde(default)]
+    pub trash: TrashConfig,
+}
+
+impl AppConfig {
+    pub fn load(path: &Path) -> Result<AppConfig, ConfigError> {
+        Config::builder()
+            .add_source(config::File::from(path))
+            // Add in settings from the environment (with a prefix of TRACK_WORK)
+            // Eg.. `TRACK_WORK_DEBUG=1 ./target/app` would set the `debug` key
+            .add_source(config::Environment::with_prefix("TRACK_WORK"))
+            .build()?
+            .try_deserialize::<AppConfig>()
+    }
+
+    /// Whether time of `kind` counts as working time. Kinds that are not configured count unless
+    /// they are pauses.
+    pub fn is_working(&self, kind: &TimeKind) -> bool {
+        self.kind_config(kind)
+            .map_or(kind.is_working_by_default(), |config| config.working)
+    }
+
+    /// Whether time of `kind` is booked on the clients of the project, which needs it to be
+    /// working time as well.
+    pub fn is_billable(&self, kind: &TimeKind) -> bool {
+        self.is_working(kind)
+            && self
+                .kind_config(kind)
+                .map_or(kind.is_working_by_default(), |config| config.billable)
+    }
+
+    /// The tags every new record of `project` starts with.
+    pub fn project_tags(&self, project: &str) -> BTreeSet<String> {
+        self.projects
+            .iter()
+            .find(|config| config.name == project)
+            .map(|config| config.tags.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    fn kind_config(&self, kind: &TimeKind) -> Option<&TimeKindConfig> {
+        self.kinds.iter().find(|config| config.name == kind.name())
+    }
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -19,6 +80,26 @@ pub struct ProjectConfig {
     pub windows: Vec<String>,
     #[serde(default)]
     pub clients: Vec<ProjectClient>,
+    /// tags of every new record of the project
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TagConfig {
+    pub name: String,
+    #[serde(default)]
+    pub windows: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TimeKindConfig {
+    /// as stored in the segments, e.g. `Meeting`
+    pub name: String,
+    #[serde(default = "default_true")]
+    pub working: bool,
+    #[serde(default = "default_true")]
+    pub billable: bool,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -41,18 +122,134 @@ pub struct LoggingConfig {
     pub window_change: bool,
 }
 
-#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct BreakConfig {
+    /// windows that pause the active record, like the lock screen
     #[serde(default)]
     pub windows: Vec<String>,
     #[serde(default, alias = "autoResume")]
     pub auto_resume: bool,
+    /// offered when pausing, no menu is shown if there are none
+    #[serde(default = "default_break_reasons")]
+    pub reasons: Vec<String>,
+}
+
+impl Default for BreakConfig {
+    fn default() -> Self {
+        BreakConfig {
+            windows: vec![],
+            auto_resume: false,
+            reasons: default_break_reasons(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SuspendConfig {
+    /// gaps between two ticks longer than this (in seconds) are treated as suspend or clock jump
+    #[serde(default = "default_gap_threshold", alias = "gapThreshold")]
+    pub gap_threshold: u64,
+}
+
+impl Default for SuspendConfig {
+    fn default() -> Self {
+        SuspendConfig {
+            gap_threshold: default_gap_threshold(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct HeartbeatConfig {
+    /// seconds between two heartbeats of the active record, records without a heartbeat for twice
+    /// as long are considered stale on startup
+    #[serde(default = "default_heartbeat_interval")]
+    pub interval: u64,
+}
+
+impl Default for HeartbeatConfig {
+    fn default() -> Self {
+        HeartbeatConfig {
+            interval: default_heartbeat_interval(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TrashConfig {
+    /// days deleted records are kept in the trash before they are removed for good
+    #[serde(default = "default_trash_retention")]
+    pub retention: u64,
+}
+
+impl Default for TrashConfig {
+    fn default() -> Self {
+        TrashConfig {
+            retention: default_trash_retention(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct ShutdownConfig {
+    /// what happens to the active record when the process is terminated from the outside
+    #[serde(default, alias = "onSignal")]
+    pub on_signal: ShutdownAction,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ShutdownAction {
+    /// finish the active record
+    #[default]
+    Stop,
+    /// pause the active record and keep it open, so it can be resumed on the next start
+    Pause,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct StorageConfig {
+    /// how the records are stored in the data directory, see `track-work import` to switch
+    #[serde(default)]
+    pub backend: StorageBackend,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageBackend {
+    /// one JSON file per week and a journal of the latest changes
+    #[default]
+    Json,
+    /// a single SQLite database, `work_records.sqlite`
+    Sqlite,
+}
+
+fn default_break_reasons() -> Vec<String> {
+    ["lunch", "coffee", "private call", "interruption"]
+        .map(str::to_string)
+        .to_vec()
+}
+
+fn default_true() -> bool {
+    true
 }
 
 fn default_ratio() -> f64 {
     1.
 }
 
+fn default_gap_threshold() -> u64 {
+    120
+}
+
+fn default_heartbeat_interval() -> u64 {
+    60
+}
+
+fn default_trash_retention() -> u64 {
+    30
+}
+
 #[cfg(test)]
 mod tests {
     use config::{File, FileFormat};
@@ -80,6 +277,8 @@ projects:
       - name: Maintenance
         ratio: 0.25
   - name: Swag
+    tags:
+      - support
 
 clients:
   - name: XO
@@ -93,13 +292,34 @@ clients:
       psp: IT.3
   - name: Without data
 
+tags:
+  - name: meeting
+    windows:
+      - Zoom Meeting
+
+kinds:
+  - name: Meeting
+  - name: Lunch
+    working: false
+  - name: Travel
+    billable: false
+
 breaks:
   windows:
     - Test
   autoResume: true
 
 logging:
-  windowChange: true",
+  windowChange: true
+
+shutdown:
+  onSignal: pause
+
+storage:
+  backend: sqlite
+
+trash:
+  retention: 7",
                 FileFormat::Yaml,
             ))
             .build();
@@ -117,5 +337,22 @@ logging:
         assert_eq!(app_cfg.clients[0].data["psp"], "IT.1");
         assert_eq!(app_cfg.breaks.windows[0], "Test");
         assert!(app_cfg.breaks.auto_resume);
+        assert_eq!(app_cfg.breaks.reasons[0], "lunch");
+        assert_eq!(app_cfg.suspend.gap_threshold, 120);
+        assert_eq!(app_cfg.heartbeat.interval, 60);
+        assert_eq!(app_cfg.shutdown.on_signal, ShutdownAction::Pause);
+        assert_eq!(app_cfg.storage.backend, StorageBackend::Sqlite);
+        assert_eq!(app_cfg.trash.retention, 7);
+        assert_eq!(app_cfg.projects[2].tags, vec!["support"]);
+        assert!(app_cfg.projects[0].tags.is_empty());
+        assert_eq!(app_cfg.tags[0].name, "meeting");
+        assert_eq!(app_cfg.tags[0].windows[0], "Zoom Meeting");
+        let kind = |name: &str| TimeKind::from(name.to_string());
+        assert!(app_cfg.is_working(&kind("Meeting")) && app_cfg.is_billable(&kind("Meeting")));
+        assert!(!app_cfg.is_working(&kind("Lunch")) && !app_cfg.is_billable(&kind("Lunch")));
+        assert!(app_cfg.is_working(&kind("Travel")) && !app_cfg.is_billable(&kind("Travel")));
+        assert!(app_cfg.is_billable(&TimeKind::Productive));
+        assert!(app_cfg.is_billable(&kind("On-call")));
+        assert!(!app_cfg.is_working(&TimeKind::Pause));
     }
 }
@@ -0,0 +1,36 @@
+use std::error::Error;
+use std::path::PathBuf;
+
+use argh::FromArgs;
+
+use crate::app::lock_instance;
+use crate::paths::Paths;
+use crate::repository::json::JsonRepository;
+use crate::repository::work_record::{self, WorkRecordRepository};
+use crate::SETTINGS;
+
+/// write the records of the profile to JSON week files
+#[derive(Debug, FromArgs)]
+#[argh(subcommand, name = "export")]
+pub struct ExportCommand {
+    /// data directory to export to, the week files are written to its `work_records/`
+    #[argh(positional)]
+    to: PathBuf,
+}
+
+impl ExportCommand {
+    pub fn run(self, paths: &Paths) -> Result<(), Box<dyn Error>> {
+        let _instance = lock_instance(paths)?;
+        let storage = SETTINGS.read().unwrap().storage.clone();
+        let source = work_record::open(&paths.data_dir, &storage)?;
+        let mut target = JsonRepository::new(&self.to)?;
+        let records = source.all()?;
+        let count = records.len();
+        for record in records {
+            target.persist(record)?;
+        }
+        target.compact()?;
+        println!("exported {} records to {}", count, self.to.display());
+        Ok(())
+    }
+}
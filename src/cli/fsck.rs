@@ -0,0 +1,63 @@
+use std::error::Error;
+
+use argh::FromArgs;
+
+use crate::app::lock_instance;
+use crate::paths::Paths;
+use crate::repository::check::{check, repair};
+use crate::repository::work_record;
+use crate::SETTINGS;
+
+/// check the records for inconsistencies
+#[derive(Debug, FromArgs)]
+#[argh(subcommand, name = "fsck")]
+pub struct FsckCommand {
+    /// fix what can be fixed without guessing, the app must not be running
+    #[argh(switch)]
+    repair: bool,
+}
+
+impl FsckCommand {
+    pub fn run(self, paths: &Paths) -> Result<(), Box<dyn Error>> {
+        let _instance = if self.repair {
+            Some(lock_instance(paths)?)
+        } else {
+            None
+        };
+        let config = SETTINGS.read().unwrap().clone();
+        let projects: Vec<String> = config.projects.iter().map(|p| p.name.clone()).collect();
+        let mut repository = work_record::open(&paths.data_dir, &config.storage)?;
+
+        let records = repository.all()?;
+        let problems = check(&records, &repository.misplaced()?, &projects);
+        for problem in &problems {
+            println!("{problem}");
+        }
+        println!("checked {} records", records.len());
+        if problems.is_empty() {
+            return Ok(());
+        }
+        if !self.repair {
+            return Err(format!(
+                "found {} problems, run with --repair to fix what can be fixed",
+                problems.len()
+            )
+            .into());
+        }
+
+        let repaired = repair(&problems);
+        for record in &repaired {
+            repository.persist(record.clone())?;
+        }
+        println!("repaired {} records", repaired.len());
+        let remaining = check(&repository.all()?, &repository.misplaced()?, &projects);
+        if remaining.is_empty() {
+            Ok(())
+        } else {
+            for problem in &remaining {
+                println!("{problem}");
+            }
+            Err(format!("{} problems have to be fixed by hand", remaining.len()).into())
+        }
+    }
+}
@@ -0,0 +1,41 @@
+use std::error::Error;
+use std::path::PathBuf;
+
+use argh::FromArgs;
+
+use crate::app::lock_instance;
+use crate::paths::Paths;
+use crate::repository::json::JsonRepository;
+use crate::repository::work_record;
+use crate::SETTINGS;
+
+/// copy the records of JSON week files into the storage of the profile
+#[derive(Debug, FromArgs)]
+#[argh(subcommand, name = "import")]
+pub struct ImportCommand {
+    /// data directory to import from, the one that contains `work_records/`
+    #[argh(positional)]
+    from: PathBuf,
+}
+
+impl ImportCommand {
+    pub fn run(self, paths: &Paths) -> Result<(), Box<dyn Error>> {
+        if !self.from.join("work_records").is_dir() {
+            return Err(format!("{} contains no work_records", self.from.display()).into());
+        }
+        let _instance = lock_instance(paths)?;
+        let storage = SETTINGS.read().unwrap().storage.clone();
+        let records = JsonRepository::read_all(&self.from)?;
+        let mut target = work_record::open(&paths.data_dir, &storage)?;
+        let count = records.len();
+        for record in records {
+            target.persist(record)?;
+        }
+        println!(
+            "imported {} records into {}",
+            count,
+            paths.data_dir.display()
+        );
+        Ok(())
+    }
+}
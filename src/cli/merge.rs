@@ -0,0 +1,47 @@
+use std::error::Error;
+
+use argh::FromArgs;
+use chrono::{Days, Local};
+
+use crate::app::lock_instance;
+use crate::paths::Paths;
+use crate::repository::work_record;
+use crate::time::{format_local, parse_local};
+use crate::SETTINGS;
+
+/// merge the record running at a point in time with the next one of its project, the app must
+/// not be running
+#[derive(Debug, FromArgs)]
+#[argh(subcommand, name = "merge")]
+pub struct MergeCommand {
+    /// a point in time within the first record, "YYYY-MM-DD HH:MM" or "HH:MM" for today
+    #[argh(option)]
+    at: String,
+}
+
+impl MergeCommand {
+    pub fn run(self, paths: &Paths) -> Result<(), Box<dyn Error>> {
+        let _instance = lock_instance(paths)?;
+        let at = parse_local(&self.at, Local::now().date_naive())
+            .ok_or_else(|| format!("'{}' is not a point in time", self.at))?;
+        let config = SETTINGS.read().unwrap().clone();
+        let mut repository = work_record::open(&paths.data_dir, &config.storage)?;
+        let record = repository
+            .find_range(at, at + chrono::Duration::nanoseconds(1), None)?
+            .into_iter()
+            .next()
+            .ok_or_else(|| format!("nothing was worked on at {}", format_local(&at)))?;
+        let end = record
+            .end
+            .ok_or_else(|| format!("{} is still running", record.name))?;
+        let next = repository
+            .find_range(end, end + Days::new(1), Some(&record.name))?
+            .into_iter()
+            .filter(|next| next.id != record.id && next.start >= end)
+            .min_by_key(|next| next.start)
+            .ok_or_else(|| format!("{} is not continued within a day", record.name))?;
+        let merged = repository.merge(&record, &next)?;
+        println!("{}", merged.display(|kind| config.is_working(kind)));
+        Ok(())
+    }
+}
@@ -0,0 +1,51 @@
+use std::error::Error;
+
+use argh::FromArgs;
+
+use crate::cli::export::ExportCommand;
+use crate::cli::fsck::FsckCommand;
+use crate::cli::import::ImportCommand;
+use crate::cli::merge::MergeCommand;
+use crate::cli::report::ReportCommand;
+use crate::cli::split::SplitCommand;
+use crate::cli::start::StartCommand;
+use crate::cli::upgrade::UpgradeCommand;
+use crate::paths::Paths;
+
+mod export;
+mod fsck;
+mod import;
+mod merge;
+mod report;
+mod split;
+mod start;
+mod upgrade;
+
+/// Commands that work on the records without starting the app.
+#[derive(Debug, FromArgs)]
+#[argh(subcommand)]
+pub enum Command {
+    Import(ImportCommand),
+    Export(ExportCommand),
+    Report(ReportCommand),
+    Upgrade(UpgradeCommand),
+    Fsck(FsckCommand),
+    Split(SplitCommand),
+    Merge(MergeCommand),
+    Start(StartCommand),
+}
+
+impl Command {
+    pub fn run(self, paths: &Paths) -> Result<(), Box<dyn Error>> {
+        match self {
+            Command::Import(command) => command.run(paths),
+            Command::Export(command) => command.run(paths),
+            Command::Report(command) => command.run(paths),
+            Command::Upgrade(command) => command.run(paths),
+            Command::Fsck(command) => command.run(paths),
+            Command::Split(command) => command.run(paths),
+            Command::Merge(command) => command.run(paths),
+            Command::Start(command) => command.run(paths),
+        }
+    }
+}
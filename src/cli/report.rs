@@ -0,0 +1,64 @@
+use std::error::Error;
+
+use argh::FromArgs;
+use chrono::{Days, NaiveDate};
+
+use crate::paths::Paths;
+use crate::report::Report;
+use crate::repository::model::TimeKind;
+use crate::repository::work_record;
+use crate::time::local_midnight;
+use crate::SETTINGS;
+
+/// print the hours per client or tag between two days
+#[derive(Debug, FromArgs)]
+#[argh(subcommand, name = "report")]
+pub struct ReportCommand {
+    /// first day of the report (YYYY-MM-DD)
+    #[argh(option)]
+    from: NaiveDate,
+    /// last day of the report, inclusive (YYYY-MM-DD)
+    #[argh(option)]
+    to: NaiveDate,
+    /// only count the records of this project
+    #[argh(option)]
+    project: Option<String>,
+    /// list every record with its description below the totals
+    #[argh(switch)]
+    details: bool,
+    /// sum up the hours per tag instead of per client
+    #[argh(switch)]
+    by_tag: bool,
+    /// list the breaks per reason and day below the totals
+    #[argh(switch)]
+    breaks: bool,
+}
+
+impl ReportCommand {
+    pub fn run(self, paths: &Paths) -> Result<(), Box<dyn Error>> {
+        if self.from > self.to {
+            return Err(format!("{} is after {}", self.from, self.to).into());
+        }
+        let config = SETTINGS.read().unwrap().clone();
+        let working = |kind: &TimeKind| config.is_working(kind);
+        let repository = work_record::open(&paths.data_dir, &config.storage)?;
+        let records = repository.find_range(
+            local_midnight(self.from),
+            local_midnight(self.to + Days::new(1)),
+            self.project.as_deref(),
+        )?;
+        let mut report = if self.by_tag {
+            Report::new_by_tag(&records, self.from, self.to, &working)
+        } else {
+            Report::new_pct(&records, self.from, self.to, &config)
+        };
+        if self.details {
+            report.add_details(&records, self.from, self.to, &working);
+        }
+        if self.breaks {
+            report.add_breaks(&records, self.from, self.to);
+        }
+        print!("{}", report);
+        Ok(())
+    }
+}
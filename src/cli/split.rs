@@ -0,0 +1,46 @@
+use std::error::Error;
+
+use argh::FromArgs;
+use chrono::Local;
+
+use crate::app::lock_instance;
+use crate::paths::Paths;
+use crate::repository::work_record;
+use crate::time::{format_local, parse_local};
+use crate::SETTINGS;
+
+/// split the record running at a point in time in two, the app must not be running
+#[derive(Debug, FromArgs)]
+#[argh(subcommand, name = "split")]
+pub struct SplitCommand {
+    /// where to split, "YYYY-MM-DD HH:MM" or "HH:MM" for today
+    #[argh(option)]
+    at: String,
+    /// project of the second part, defaults to the project of the record
+    #[argh(option)]
+    project: Option<String>,
+}
+
+impl SplitCommand {
+    pub fn run(self, paths: &Paths) -> Result<(), Box<dyn Error>> {
+        let _instance = lock_instance(paths)?;
+        let at = parse_local(&self.at, Local::now().date_naive())
+            .ok_or_else(|| format!("'{}' is not a point in time", self.at))?;
+        let config = SETTINGS.read().unwrap().clone();
+        if let Some(project) = &self.project {
+            if !config.projects.iter().any(|p| p.name == *project) {
+                return Err(format!("{project} is missing in the config").into());
+            }
+        }
+        let mut repository = work_record::open(&paths.data_dir, &config.storage)?;
+        let record = repository
+            .find_range(at, at + chrono::Duration::nanoseconds(1), None)?
+            .into_iter()
+            .next()
+            .ok_or_else(|| format!("nothing was worked on at {}", format_local(&at)))?;
+        let (first, second) = repository.split(&record, at, self.project.as_deref())?;
+        println!("{}", first.display(|kind| config.is_working(kind)));
+        println!("{}", second.display(|kind| config.is_working(kind)));
+        Ok(())
+    }
+}
@@ -0,0 +1,54 @@
+use std::error::Error;
+
+use argh::FromArgs;
+use chrono::{Local, Utc};
+
+use crate::app::lock_instance;
+use crate::history::{start_at, validate};
+use crate::paths::Paths;
+use crate::repository::work_record;
+use crate::time::parse_local;
+use crate::SETTINGS;
+
+/// start a project at a past time because switching to it was forgotten, the latest record is cut
+/// off there. The app must not be running, it continues with the new record when started.
+#[derive(Debug, FromArgs)]
+#[argh(subcommand, name = "start")]
+pub struct StartCommand {
+    /// the project that has been worked on since `at`
+    #[argh(positional)]
+    project: String,
+    /// since when, "YYYY-MM-DD HH:MM" or "HH:MM" for today
+    #[argh(option)]
+    at: String,
+}
+
+impl StartCommand {
+    pub fn run(self, paths: &Paths) -> Result<(), Box<dyn Error>> {
+        let _instance = lock_instance(paths)?;
+        let at = parse_local(&self.at, Local::now().date_naive())
+            .ok_or_else(|| format!("'{}' is not a point in time", self.at))?;
+        let config = SETTINGS.read().unwrap().clone();
+        if !config.projects.iter().any(|p| p.name == self.project) {
+            return Err(format!("{} is missing in the config", self.project).into());
+        }
+        let mut repository = work_record::open(&paths.data_dir, &config.storage)?;
+        let latest = repository.get_latest();
+        let tags = config.project_tags(&self.project);
+        let (cut, record) = start_at(latest.as_ref(), &self.project, tags, at, Utc::now())?;
+
+        let mut others = repository.find_range(at, Utc::now(), None)?;
+        if let Some(cut) = &cut {
+            others.retain(|other| other.id != cut.id);
+            others.push(cut.clone());
+        }
+        validate(&record, &others).map_err(|problem| problem.to_string())?;
+        if let Some(cut) = cut {
+            repository.persist(cut.clone())?;
+            println!("{}", cut.display(|kind| config.is_working(kind)));
+        }
+        repository.persist(record.clone())?;
+        println!("{}", record.display(|kind| config.is_working(kind)));
+        Ok(())
+    }
+}
@@ -0,0 +1,28 @@
+use std::error::Error;
+
+use argh::FromArgs;
+
+use crate::paths::Paths;
+use crate::repository::work_record;
+use crate::SETTINGS;
+
+/// rewrite all records stored by older versions in the current format, after backing them up
+#[derive(Debug, FromArgs)]
+#[argh(subcommand, name = "upgrade")]
+pub struct UpgradeCommand {}
+
+impl UpgradeCommand {
+    pub fn run(self, paths: &Paths) -> Result<(), Box<dyn Error>> {
+        let storage = SETTINGS.read().unwrap().storage.clone();
+        let mut repository = work_record::open(&paths.data_dir, &storage)?;
+        match repository.upgrade()? {
+            Some(backup) => println!(
+                "upgraded the records in {}, the previous version is kept in {}",
+                paths.data_dir.display(),
+                backup.display()
+            ),
+            None => println!("the records in {} are up to date", paths.data_dir.display()),
+        }
+        Ok(())
+    }
+}
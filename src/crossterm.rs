@@ -14,10 +14,25 @@ use tui::{
     Terminal,
 };
 
+use crate::repository::lock::InstanceLock;
+use crate::shutdown::Termination;
+#[cfg(windows)]
 use crate::win::focus_watcher::watch_foreground_windows;
 use crate::{app::App, ui};
 
-pub fn run(tick_rate: Duration) -> Result<(), Box<dyn Error>> {
+/// Window titles are only available on windows, elsewhere projects are never switched
+/// automatically.
+#[cfg(not(windows))]
+fn watch_foreground_windows(
+    _polling_interval: Duration,
+    _threshold: Duration,
+) -> std::sync::mpsc::Receiver<String> {
+    std::sync::mpsc::sync_channel(1).1
+}
+
+pub fn run(tick_rate: Duration, instance: InstanceLock) -> Result<(), Box<dyn Error>> {
+    let termination = Termination::register()?;
+
     // setup terminal
     enable_raw_mode()?;
     let mut stdout = io::stdout();
@@ -26,10 +41,11 @@ pub fn run(tick_rate: Duration) -> Result<(), Box<dyn Error>> {
     let mut terminal = Terminal::new(backend)?;
 
     // create app and run it
-    let app = App::new("Track Work");
-    let res = run_app(&mut terminal, app, tick_rate);
+    let app = App::new("Track Work", instance);
+    let res = run_app(&mut terminal, app, tick_rate, &termination);
+    termination.complete();
 
-    // restore terminal
+    // restore terminal, which fails if it was closed, but everything is saved at this point
     disable_raw_mode()?;
     execute!(
         terminal.backend_mut(),
@@ -49,16 +65,36 @@ fn run_app<B: Backend>(
     terminal: &mut Terminal<B>,
     mut app: App,
     tick_rate: Duration,
+    termination: &Termination,
+) -> io::Result<()> {
+    let res = run_event_loop(terminal, &mut app, tick_rate, termination);
+    // losing the terminal is as good as being terminated
+    if res.is_err() || termination.is_requested() {
+        app.on_termination();
+    }
+    res
+}
+
+fn run_event_loop<B: Backend>(
+    terminal: &mut Terminal<B>,
+    app: &mut App,
+    tick_rate: Duration,
+    termination: &Termination,
 ) -> io::Result<()> {
     let mut last_tick = Instant::now();
     let rx = watch_foreground_windows(Duration::from_millis(500), Duration::from_secs(30));
     loop {
-        terminal.draw(|f| ui::draw(f, &mut app))?;
+        if termination.is_requested() {
+            return Ok(());
+        }
+        terminal.draw(|f| ui::draw(f, app))?;
 
         let timeout = tick_rate
             .checked_sub(last_tick.elapsed())
             .unwrap_or_else(|| Duration::from_secs(0));
-        if event::poll(timeout)? {
+        let has_event = event::poll(timeout)?;
+        app.check_clock();
+        if has_event {
             if let Event::Key(key) = event::read()? {
                 app.on_input(key);
             }
@@ -0,0 +1,391 @@
+use std::collections::BTreeSet;
+
+use chrono::{DateTime, Local, NaiveDate, Utc};
+
+use crate::repository::check::{check, Problem};
+use crate::repository::model::{Extra, ProjectState, TimeKind, TimeSegment, WorkRecord};
+use crate::time::{format_local, parse_local};
+
+/// What the user can change of a record from within the app.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RecordField {
+    Project,
+    Start,
+    /// only records that are done have an end
+    End,
+    /// entered as `HH:MM-HH:MM Kind reason`, separated by commas
+    Segments,
+    Description,
+    /// entered separated by spaces or commas
+    Tags,
+    State,
+}
+
+impl RecordField {
+    pub const ALL: [RecordField; 7] = [
+        RecordField::Project,
+        RecordField::Start,
+        RecordField::End,
+        RecordField::Segments,
+        RecordField::Description,
+        RecordField::Tags,
+        RecordField::State,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            RecordField::Project => "project",
+            RecordField::Start => "start",
+            RecordField::End => "end",
+            RecordField::Segments => "segments",
+            RecordField::Description => "description",
+            RecordField::Tags => "tags",
+            RecordField::State => "state",
+        }
+    }
+
+    /// whether setting the field may move the record or its segments
+    pub fn changes_times(self) -> bool {
+        matches!(
+            self,
+            RecordField::Start | RecordField::End | RecordField::Segments | RecordField::State
+        )
+    }
+
+    /// the field of `record` as it is shown to the user
+    pub fn value(self, record: &WorkRecord) -> String {
+        match self {
+            RecordField::Project => record.name.clone(),
+            RecordField::Start => format_time(&record.start),
+            RecordField::End => record.end.as_ref().map(format_time).unwrap_or_default(),
+            RecordField::Segments => record
+                .segments
+                .iter()
+                .map(format_segment)
+                .collect::<Vec<_>>()
+                .join(", "),
+            RecordField::Description => record.description.clone().unwrap_or_default(),
+            RecordField::Tags => Vec::from_iter(record.tags.iter().cloned()).join(" "),
+            RecordField::State => format!("{:?}", record.state),
+        }
+    }
+
+    /// Sets the field of `record` to `input`, an empty input clears optional fields. Times are
+    /// taken to be on the day the record starts unless they come with a date.
+    pub fn set(self, record: &mut WorkRecord, input: &str) -> Result<(), String> {
+        let input = input.trim();
+        let day = record.start.with_timezone(&Local).date_naive();
+        match self {
+            RecordField::Project if input.is_empty() => return Err("needs a project".into()),
+            RecordField::Project => record.name = input.to_string(),
+            RecordField::Start => {
+                let start = parse_time(input, day)?;
+                let first = record.segments.first_mut().ok_or("has no segments")?;
+                if first.end.is_some_and(|end| start >= end) {
+                    return Err("can't start after its first segment ends".into());
+                }
+                first.start = start;
+                record.start = start;
+            }
+            RecordField::End => {
+                if record.state != ProjectState::Done {
+                    return Err("is still running, stop it instead".into());
+                }
+                let end = parse_time(input, day)?;
+                let last = record.segments.last_mut().ok_or("has no segments")?;
+                if end <= last.start {
+                    return Err("can't end before its last segment starts".into());
+                }
+                last.end = Some(end);
+                record.end = Some(end);
+            }
+            RecordField::Segments => set_segments(record, parse_segments(input, day)?)?,
+            RecordField::Description => {
+                record.description = Some(input.to_string()).filter(|d| !d.is_empty())
+            }
+            RecordField::Tags => {
+                record.tags = input
+                    .split(|c: char| c.is_whitespace() || c == ',')
+                    .filter(|tag| !tag.is_empty())
+                    .map(str::to_string)
+                    .collect()
+            }
+            RecordField::State => {
+                let next = match input {
+                    "Working" => ProjectState::Working,
+                    "Paused" => ProjectState::Paused,
+                    "Done" => ProjectState::Done,
+                    _ => return Err(format!("'{input}' is not a state")),
+                };
+                if next != record.state {
+                    let at = record.last_activity();
+                    record.transition(next, at).map_err(|e| e.to_string())?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A record of work that was forgotten to be tracked, done from `start` to `end`.
+pub fn manual_record(
+    name: String,
+    tags: BTreeSet<String>,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> WorkRecord {
+    let mut record = WorkRecord::new(name, tags, start);
+    record.close_at(end);
+    record
+}
+
+/// Starts `name` at `at` instead of now, because switching to it was forgotten. The `latest`
+/// record is cut off at `at` if it reaches beyond it. Returns the cut off record, if it had to be
+/// changed, and the new one.
+pub fn start_at(
+    latest: Option<&WorkRecord>,
+    name: &str,
+    tags: BTreeSet<String>,
+    at: DateTime<Utc>,
+    now: DateTime<Utc>,
+) -> Result<(Option<WorkRecord>, WorkRecord), String> {
+    if at > now {
+        return Err(format!("{} is in the future", format_local(&at)));
+    }
+    let latest = match latest {
+        Some(latest) if latest.name == name && latest.state != ProjectState::Done => {
+            return Err(format!("{name} is already running"))
+        }
+        Some(latest) if latest.start >= at => {
+            return Err(format!(
+                "{} only started at {}",
+                latest.name,
+                format_local(&latest.start)
+            ))
+        }
+        Some(latest) if latest.end.is_none_or(|end| end > at) => {
+            let mut latest = latest.clone();
+            latest.close_at(at);
+            latest.last_seen = None;
+            Some(latest)
+        }
+        _ => None,
+    };
+    let mut record = WorkRecord::new(name.to_string(), tags, at);
+    // it has been active ever since
+    record.last_seen = Some(now);
+    Ok((latest, record))
+}
+
+/// Makes sure `record` can be saved next to `others`, which may contain a previous version of it.
+/// Only the problems of `record` count, see [check].
+pub fn validate(record: &WorkRecord, others: &[WorkRecord]) -> Result<(), Box<Problem>> {
+    let mut records: Vec<WorkRecord> = others
+        .iter()
+        .filter(|other| other.id != record.id)
+        .cloned()
+        .collect();
+    records.push(record.clone());
+    let problem = check(&records, &[], &[])
+        .into_iter()
+        .find(|problem| match problem {
+            Problem::EndBeforeStart(other)
+            | Problem::SegmentsOutOfOrder(other)
+            | Problem::SegmentsOverlap(other) => other.id == record.id,
+            Problem::Overlap(first, second) => first.id == record.id || second.id == record.id,
+            // the app only ever keeps the latest record open and stops it before creating another
+            Problem::MultipleOpen(_) | Problem::WrongWeek(_) | Problem::UnknownProject(_) => false,
+        });
+    match problem {
+        Some(problem) => Err(Box::new(problem)),
+        None => Ok(()),
+    }
+}
+
+fn format_time(time: &DateTime<Utc>) -> String {
+    time.with_timezone(&Local).format("%H:%M").to_string()
+}
+
+fn parse_time(input: &str, day: NaiveDate) -> Result<DateTime<Utc>, String> {
+    parse_local(input, day).ok_or_else(|| format!("'{input}' is not a time (HH:MM)"))
+}
+
+fn format_segment(segment: &TimeSegment) -> String {
+    let end = segment.end.as_ref().map(format_time).unwrap_or_default();
+    let mut text = format!(
+        "{}-{} {}",
+        format_time(&segment.start),
+        end,
+        segment.kind.name()
+    );
+    if let Some(reason) = &segment.reason {
+        text.push(' ');
+        text.push_str(reason);
+    }
+    text
+}
+
+fn parse_segments(input: &str, day: NaiveDate) -> Result<Vec<TimeSegment>, String> {
+    input
+        .split(',')
+        .map(|part| {
+            let part = part.trim();
+            let (times, rest) = part
+                .split_once(' ')
+                .ok_or_else(|| format!("'{part}' needs a kind"))?;
+            let (start, end) = times
+                .split_once('-')
+                .ok_or_else(|| format!("'{times}' is not a range of times (HH:MM-HH:MM)"))?;
+            let (kind, reason) = rest.trim().split_once(' ').unwrap_or((rest.trim(), ""));
+            Ok(TimeSegment {
+                start: parse_time(start, day)?,
+                end: match end.trim() {
+                    "" => None,
+                    end => Some(parse_time(end, day)?),
+                },
+                kind: TimeKind::from(kind.to_string()),
+                reason: Some(reason.trim().to_string()).filter(|r| !r.is_empty()),
+                extra: Extra::new(),
+            })
+        })
+        .collect()
+}
+
+/// Replaces the segments of `record`, they have to follow each other without gaps and only the
+/// last one of a running record stays open.
+fn set_segments(record: &mut WorkRecord, mut segments: Vec<TimeSegment>) -> Result<(), String> {
+    for (segment, next) in segments.iter().zip(segments.iter().skip(1)) {
+        if segment.end != Some(next.start) {
+            return Err("segments have to follow each other without gaps".into());
+        }
+    }
+    let (first, last) = match (segments.first(), segments.last()) {
+        (Some(first), Some(last)) => (first, last),
+        _ => return Err("needs at least one segment".into()),
+    };
+    match (&record.state, last.end) {
+        (ProjectState::Done, None) => return Err("the last segment has to end".into()),
+        (ProjectState::Done, Some(end)) => record.end = Some(end),
+        (_, Some(_)) => return Err("the last segment of a running record has to stay open".into()),
+        (_, None) => {
+            record.state = match last.kind {
+                TimeKind::Pause => ProjectState::Paused,
+                _ => ProjectState::Working,
+            }
+        }
+    }
+    record.start = first.start;
+    // the extra fields of segments that start where they did before are kept
+    for segment in segments.iter_mut() {
+        if let Some(previous) = record.segments.iter().find(|s| s.start == segment.start) {
+            segment.extra = previous.extra.clone();
+        }
+    }
+    record.segments = segments;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+
+    use super::*;
+
+    fn day() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2023, 7, 4).unwrap()
+    }
+
+    fn at(time: &str) -> DateTime<Utc> {
+        parse_local(time, day()).unwrap()
+    }
+
+    #[test]
+    fn test_segments() {
+        let mut record = manual_record("EKS".into(), BTreeSet::new(), at("08:00"), at("10:00"));
+        let segments = "07:30-09:00 Productive, 09:00-09:15 Pause lunch break, 09:15-11:00 Meeting";
+
+        RecordField::Segments.set(&mut record, segments).unwrap();
+
+        assert_eq!(RecordField::Segments.value(&record), segments);
+        assert_eq!(record.start, at("07:30"));
+        assert_eq!(record.end, Some(at("11:00")));
+        assert_eq!(record.segments[1].reason.as_deref(), Some("lunch break"));
+        assert_eq!(
+            RecordField::Segments.set(&mut record, "07:30-09:00 Productive, 09:30-11:00 Meeting"),
+            Err("segments have to follow each other without gaps".to_string())
+        );
+        assert!(RecordField::Segments
+            .set(&mut record, "07:30-09:00 Productive, 09:00- Pause")
+            .is_err());
+        assert!(RecordField::Segments
+            .set(&mut record, "07:30 Productive")
+            .is_err());
+        assert_eq!(record.segments.len(), 3);
+    }
+
+    #[test]
+    fn test_start_end_and_state() {
+        let mut record = manual_record("EKS".into(), BTreeSet::new(), at("08:00"), at("10:00"));
+
+        RecordField::Start.set(&mut record, "07:45").unwrap();
+        RecordField::End.set(&mut record, "10:30").unwrap();
+        assert!(RecordField::Start.set(&mut record, "11:00").is_err());
+        assert!(RecordField::End.set(&mut record, "7:00").is_err());
+        assert!(RecordField::State.set(&mut record, "Working").is_err());
+
+        assert_eq!(record.start, at("07:45"));
+        assert_eq!(record.segments[0].start, at("07:45"));
+        assert_eq!(record.end, Some(at("10:30")));
+        assert_eq!(record.segments[0].end, Some(at("10:30")));
+        assert_eq!(
+            record.calculate_duration(TimeKind::is_working_by_default),
+            Duration::minutes(165)
+        );
+    }
+
+    #[test]
+    fn test_start_at() {
+        let mut latest = WorkRecord::new("Swag".into(), BTreeSet::new(), at("09:00"));
+        latest.pause_at(at("10:30"), None).unwrap();
+        let tags = BTreeSet::new();
+
+        let (cut, record) =
+            start_at(Some(&latest), "EKS", tags.clone(), at("10:15"), at("11:00")).unwrap();
+
+        let cut = cut.unwrap();
+        assert_eq!(cut.end, Some(at("10:15")));
+        assert_eq!(cut.state, ProjectState::Done);
+        assert_eq!(cut.segments.len(), 1);
+        assert_eq!(record.name, "EKS");
+        assert_eq!(record.start, at("10:15"));
+        assert_eq!(record.state, ProjectState::Working);
+        assert_eq!(validate(&record, std::slice::from_ref(&cut)), Ok(()));
+        let (unchanged, _) =
+            start_at(Some(&cut), "XO", tags.clone(), at("10:45"), at("11:00")).unwrap();
+        assert_eq!(unchanged, None);
+        assert!(start_at(Some(&latest), "EKS", tags.clone(), at("08:00"), at("11:00")).is_err());
+        assert!(start_at(
+            Some(&latest),
+            "Swag",
+            tags.clone(),
+            at("10:15"),
+            at("11:00")
+        )
+        .is_err());
+        assert!(start_at(None, "EKS", tags, at("12:00"), at("11:00")).is_err());
+    }
+
+    #[test]
+    fn test_validate() {
+        let first = manual_record("EKS".into(), BTreeSet::new(), at("08:00"), at("10:00"));
+        let mut second = manual_record("XO".into(), BTreeSet::new(), at("10:00"), at("11:00"));
+        assert_eq!(validate(&second, &[first.clone(), second.clone()]), Ok(()));
+
+        RecordField::Start.set(&mut second, "09:30").unwrap();
+
+        assert_eq!(
+            validate(&second, std::slice::from_ref(&first)),
+            Err(Box::new(Problem::Overlap(first, second)))
+        );
+    }
+}
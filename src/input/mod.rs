@@ -1,3 +1,4 @@
 pub mod filter_mode;
 pub mod handler;
 pub mod normal_mode;
+pub mod prompt_mode;
@@ -1,6 +1,7 @@
-use crossterm::event::{KeyCode, KeyEvent, KeyEventKind};
+use crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
 
 use crate::app::App;
+use crate::history::RecordField;
 use crate::input::handler::InputHandler;
 
 #[derive(PartialEq, Debug, Copy, Clone)]
@@ -8,40 +9,53 @@ pub struct NormalMode {}
 
 impl NormalMode {
     fn on_enter(self, app: &mut App) {
-        {
+        let Some(selection) = app.projects.get_selected() else {
+            return;
+        };
+        let project = (*selection).to_string();
+        app.undoable(format!("start {project}"), |app| {
             self.on_stop(app);
-        }
-        {
-            if let Some(selection) = app.projects.get_selected() {
-                app.start_working_on((*selection).to_string());
-            }
-        }
+            app.start_working_on(project);
+        });
     }
 
     fn on_pause(self, app: &mut App) {
-        if let Some(ref mut active_project) = app.active_project {
-            active_project.begin_pause();
-        }
+        app.undoable("pause", App::ask_pause);
     }
     fn on_resume(self, app: &mut App) {
-        if let Some(ref mut active_project) = app.active_project {
-            active_project.resume_work();
-        }
+        app.undoable("resume", |app| {
+            if let Some(ref mut active_project) = app.active_project {
+                active_project.resume_work();
+            }
+        });
     }
     fn on_stop(self, app: &mut App) {
-        if let Some(ref mut active_project) = app.active_project {
-            active_project.stop();
-        }
+        app.undoable("stop", |app| {
+            if let Some(ref mut active_project) = app.active_project {
+                active_project.stop();
+            }
+        });
     }
 }
 
 impl InputHandler for NormalMode {
     fn on_input(&self, event: KeyEvent, app: &mut App) {
         match (event.code, event.kind) {
+            (KeyCode::Char('r'), KeyEventKind::Press)
+                if event.modifiers.contains(KeyModifiers::CONTROL) =>
+            {
+                app.redo()
+            }
             (KeyCode::Enter, KeyEventKind::Press) => self.on_enter(app),
             (KeyCode::Char('p'), KeyEventKind::Press) => self.on_pause(app),
             (KeyCode::Char('r'), KeyEventKind::Press) => self.on_resume(app),
             (KeyCode::Char('s'), KeyEventKind::Press) => self.on_stop(app),
+            (KeyCode::Char('P'), KeyEventKind::Press) => app.choose_profile(),
+            (KeyCode::Char('d'), KeyEventKind::Press) => app.edit_active(RecordField::Description),
+            (KeyCode::Char('t'), KeyEventKind::Press) => app.edit_active(RecordField::Tags),
+            (KeyCode::Char('k'), KeyEventKind::Press) => app.choose_kind(),
+            (KeyCode::Char('b'), KeyEventKind::Press) => app.ask_start_at(),
+            (KeyCode::Char('u'), KeyEventKind::Press) => app.undo(),
             _ => {}
         }
     }
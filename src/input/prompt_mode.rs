@@ -0,0 +1,22 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyEventKind};
+
+use crate::app::App;
+use crate::input::handler::InputHandler;
+
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub struct PromptMode {}
+
+impl InputHandler for PromptMode {
+    fn on_input(&self, event: KeyEvent, app: &mut App) {
+        match (event.code, event.kind) {
+            (KeyCode::Enter, KeyEventKind::Press) => app.submit_prompt(),
+            (KeyCode::Esc, KeyEventKind::Press) => app.cancel_prompt(),
+            (_, KeyEventKind::Press | KeyEventKind::Repeat) => {
+                if let Some(ref mut prompt) = app.prompt {
+                    prompt.state.on_input(&event);
+                }
+            }
+            _ => {}
+        }
+    }
+}
@@ -1,36 +1,40 @@
+use std::path::PathBuf;
 use std::sync::RwLock;
 use std::{error::Error, time::Duration};
 
 use argh::FromArgs;
-use config::Config;
 use lazy_static::lazy_static;
 
+use crate::app::lock_instance;
 use crate::app_config::AppConfig;
+use crate::cli::Command;
 use crate::crossterm::run;
+use crate::paths::Paths;
 
 mod app;
+mod cli;
 mod crossterm;
 mod input;
+mod paths;
 mod repository;
+mod shutdown;
 mod ui;
 mod widgets;
+#[cfg(windows)]
 mod win;
 #[macro_use]
 mod log;
 mod app_config;
+mod history;
 mod report;
+mod time;
+mod undo;
 
 lazy_static! {
-    pub static ref SETTINGS: RwLock<AppConfig> = RwLock::new(Config::builder()
-        // Add in `./config.{yml|toml}`
-        .add_source(config::File::with_name("config"))
-        // Add in settings from the environment (with a prefix of TRACK_WORK)
-        // Eg.. `TRACK_WORK_DEBUG=1 ./target/app` would set the `debug` key
-        .add_source(config::Environment::with_prefix("TRACK_WORK"))
-        .build()
-        .unwrap()
-        .try_deserialize::<AppConfig>()
-        .expect("Config malformed"));
+    // the config file of the current profile, see `Paths` for its location
+    pub static ref SETTINGS: RwLock<AppConfig> = RwLock::new(
+        AppConfig::load(&Paths::current().config_file).expect("Config malformed")
+    );
 }
 
 /// Demo
@@ -39,11 +43,55 @@ struct Cli {
     /// time in ms between two ticks.
     #[argh(option, default = "250")]
     tick_rate: u64,
+    /// path of the config file, defaults to `TRACK_WORK_CONFIG` or the config directory of the
+    /// platform (e.g. ~/.config/track-work/config.yml)
+    #[argh(option)]
+    config: Option<PathBuf>,
+    /// directory the records are stored in, defaults to `TRACK_WORK_DATA_DIR` or the data
+    /// directory of the platform (e.g. ~/.local/share/track-work)
+    #[argh(option)]
+    data_dir: Option<PathBuf>,
+    /// profile with its own config and records, defaults to `TRACK_WORK_PROFILE` or "default"
+    #[argh(option)]
+    profile: Option<String>,
+    #[argh(subcommand)]
+    command: Option<Command>,
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
     let cli: Cli = argh::from_env();
     let tick_rate = Duration::from_millis(cli.tick_rate);
-    run(tick_rate)?;
+    let mut paths = match Paths::init(cli.config, cli.data_dir, cli.profile) {
+        Ok(paths) => paths,
+        Err(e) => {
+            eprintln!("{e}");
+            std::process::exit(1);
+        }
+    };
+    if cli.command.is_none() {
+        paths.migrate_working_directory();
+    }
+    if !paths.config_file.is_file() {
+        eprintln!(
+            "no config found at {}, see config.yml in the repository for an example",
+            paths.config_file.display()
+        );
+        std::process::exit(1);
+    }
+    if let Some(command) = cli.command {
+        if let Err(e) = command.run(&paths) {
+            eprintln!("{e}");
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+    let instance = match lock_instance(&paths) {
+        Ok(lock) => lock,
+        Err(e) => {
+            eprintln!("{e}");
+            std::process::exit(1);
+        }
+    };
+    run(tick_rate, instance)?;
     Ok(())
 }
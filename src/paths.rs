@@ -0,0 +1,344 @@
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use std::{env, fs, io};
+
+use crate::log::log;
+
+static PATHS: RwLock<Option<Paths>> = RwLock::new(None);
+
+const APP_DIR: &str = "track-work";
+const PROFILES_DIR: &str = "profiles";
+pub const DEFAULT_PROFILE: &str = "default";
+const CONFIG_EXTENSIONS: [&str; 4] = ["yml", "yaml", "toml", "json"];
+
+/// Where the configuration and the records of a profile are stored.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Paths {
+    pub profile: String,
+    pub config_file: PathBuf,
+    pub data_dir: PathBuf,
+    /// whether the config file was given explicitly, instead of using the default location
+    explicit_config: bool,
+    /// whether the data directory was given explicitly, instead of using the default location
+    explicit_data_dir: bool,
+}
+
+impl Paths {
+    /// Resolves the paths from the command line, falling back to `TRACK_WORK_CONFIG`,
+    /// `TRACK_WORK_DATA_DIR` and `TRACK_WORK_PROFILE` and then to the platform defaults (XDG
+    /// directories on linux, `%APPDATA%` on windows).
+    pub fn init(
+        config_file: Option<PathBuf>,
+        data_dir: Option<PathBuf>,
+        profile: Option<String>,
+    ) -> Result<Paths, String> {
+        let profile = profile
+            .or_else(|| env::var("TRACK_WORK_PROFILE").ok())
+            .unwrap_or_else(|| DEFAULT_PROFILE.to_string());
+        check_profile_name(&profile)?;
+        let mut paths = Paths::of_profile(&profile);
+        if let Some(config_file) =
+            config_file.or_else(|| env::var_os("TRACK_WORK_CONFIG").map(PathBuf::from))
+        {
+            paths.config_file = config_file;
+            paths.explicit_config = true;
+        }
+        if let Some(data_dir) =
+            data_dir.or_else(|| env::var_os("TRACK_WORK_DATA_DIR").map(PathBuf::from))
+        {
+            paths.data_dir = data_dir;
+            paths.explicit_data_dir = true;
+        }
+        Paths::set(paths.clone());
+        Ok(paths)
+    }
+
+    /// Migrates data found in the working directory to the default profile. Only done when the
+    /// app is started, commands may be run from any directory.
+    pub fn migrate_working_directory(&mut self) {
+        if self.profile != DEFAULT_PROFILE {
+            return;
+        }
+        match env::current_dir() {
+            Ok(cwd) => {
+                if let Err(e) = self.migrate_from(&cwd) {
+                    log!("⚠ failed to migrate data from {}: {}", cwd.display(), e);
+                }
+            }
+            Err(e) => log!("⚠ cwd is not set: {}", e),
+        }
+        Paths::set(self.clone());
+    }
+
+    /// The paths of the profile in use.
+    pub fn current() -> Paths {
+        if let Some(paths) = PATHS.read().expect("paths lock poisoned").as_ref() {
+            return paths.clone();
+        }
+        let paths = Paths::of_profile(DEFAULT_PROFILE);
+        Paths::set(paths.clone());
+        paths
+    }
+
+    /// Makes `paths` the paths of the profile in use.
+    pub fn set(paths: Paths) {
+        *PATHS.write().expect("paths lock poisoned") = Some(paths);
+    }
+
+    /// The paths of `profile` to switch to from this profile. A config file or data directory
+    /// given explicitly belongs to this profile only, so there is no other profile to switch to.
+    pub fn of_other_profile(&self, profile: &str) -> Result<Paths, String> {
+        check_profile_name(profile)?;
+        if self.explicit_config || self.explicit_data_dir {
+            return Err(
+                "profiles can't be switched while --config or --data-dir is given".to_string(),
+            );
+        }
+        Ok(Paths::of_profile(profile))
+    }
+
+    /// The default locations of a profile. The default profile lives directly in the config and
+    /// data directories of the app, all others in a `profiles/<name>` folder within them.
+    pub fn of_profile(profile: &str) -> Paths {
+        Paths::of_profile_in(profile, &config_base(), &data_base())
+    }
+
+    fn of_profile_in(profile: &str, config_base: &Path, data_base: &Path) -> Paths {
+        let (config_dir, data_dir) = if profile == DEFAULT_PROFILE {
+            (config_base.to_path_buf(), data_base.to_path_buf())
+        } else {
+            (
+                config_base.join(PROFILES_DIR).join(profile),
+                data_base.join(PROFILES_DIR).join(profile),
+            )
+        };
+        Paths {
+            profile: profile.to_string(),
+            config_file: find_config(&config_dir).unwrap_or_else(|| config_dir.join("config.yml")),
+            data_dir,
+            explicit_config: false,
+            explicit_data_dir: false,
+        }
+    }
+
+    /// Names of all profiles that have a config, the default profile always comes first.
+    pub fn profiles() -> Vec<String> {
+        profiles_in(&config_base())
+    }
+
+    /// Config and records used to live in the working directory. Copies the config and moves the
+    /// records to the default locations, unless something is already there. Paths given
+    /// explicitly are left alone.
+    fn migrate_from(&mut self, legacy_dir: &Path) -> io::Result<()> {
+        if !self.explicit_config && !self.config_file.exists() {
+            if let Some(legacy_config) = find_config(legacy_dir) {
+                let extension = legacy_config.extension().unwrap_or_default();
+                let config_file = self.config_file.with_extension(extension);
+                create_parent(&config_file)?;
+                fs::copy(&legacy_config, &config_file)?;
+                log!(
+                    "copied {} to {}",
+                    legacy_config.display(),
+                    config_file.display()
+                );
+                self.config_file = config_file;
+            }
+        }
+
+        if self.explicit_data_dir {
+            return Ok(());
+        }
+        let legacy_records = legacy_dir.join("work_records");
+        let records = self.data_dir.join("work_records");
+        if !legacy_records.is_dir() || same_path(&legacy_records, &records) {
+            return Ok(());
+        }
+        if records.exists() {
+            log!(
+                "⚠ found {}, but {} already exists, so it was not moved",
+                legacy_records.display(),
+                records.display()
+            );
+            return Ok(());
+        }
+        fs::create_dir_all(&self.data_dir)?;
+        move_dir(&legacy_records, &records)?;
+        log!(
+            "moved {} to {}",
+            legacy_records.display(),
+            records.display()
+        );
+        Ok(())
+    }
+}
+
+/// Profile names are folder names within `profiles/`, so they must not lead anywhere else.
+fn check_profile_name(profile: &str) -> Result<(), String> {
+    if profile.is_empty() || profile.contains(['/', '\\', ':']) || profile.contains("..") {
+        return Err(format!("'{profile}' is not a valid profile name"));
+    }
+    Ok(())
+}
+
+fn config_base() -> PathBuf {
+    dirs::config_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join(APP_DIR)
+}
+
+fn data_base() -> PathBuf {
+    dirs::data_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join(APP_DIR)
+}
+
+fn profiles_in(config_base: &Path) -> Vec<String> {
+    let mut profiles: Vec<String> = fs::read_dir(config_base.join(PROFILES_DIR))
+        .map(|entries| {
+            entries
+                .filter_map(Result::ok)
+                .filter(|entry| find_config(&entry.path()).is_some())
+                .map(|entry| entry.file_name().to_string_lossy().to_string())
+                .collect()
+        })
+        .unwrap_or_default();
+    profiles.sort();
+    profiles.insert(0, DEFAULT_PROFILE.to_string());
+    profiles
+}
+
+fn find_config(dir: &Path) -> Option<PathBuf> {
+    CONFIG_EXTENSIONS
+        .iter()
+        .map(|extension| dir.join("config").with_extension(extension))
+        .find(|path| path.is_file())
+}
+
+fn create_parent(path: &Path) -> io::Result<()> {
+    match path.parent() {
+        Some(parent) => fs::create_dir_all(parent),
+        None => Ok(()),
+    }
+}
+
+fn same_path(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+/// Renames `from` to `to`, copying it if both are on different file systems.
+fn move_dir(from: &Path, to: &Path) -> io::Result<()> {
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    copy_dir(from, to)?;
+    fs::remove_dir_all(from)
+}
+
+fn copy_dir(from: &Path, to: &Path) -> io::Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else {
+            fs::copy(entry.path(), target)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_migrate_from_working_directory() {
+        let legacy = tempfile::tempdir().unwrap();
+        let target = tempfile::tempdir().unwrap();
+        fs::write(legacy.path().join("config.yml"), "projects: []").unwrap();
+        fs::create_dir(legacy.path().join("work_records")).unwrap();
+        fs::write(legacy.path().join("work_records/2023-27.json"), "{}").unwrap();
+        let mut paths = Paths {
+            profile: DEFAULT_PROFILE.to_string(),
+            config_file: target.path().join("config/config.yml"),
+            data_dir: target.path().join("data"),
+            explicit_config: false,
+            explicit_data_dir: false,
+        };
+
+        paths.migrate_from(legacy.path()).unwrap();
+
+        assert!(paths.config_file.is_file());
+        assert!(target
+            .path()
+            .join("data/work_records/2023-27.json")
+            .is_file());
+        assert!(!legacy.path().join("work_records").exists());
+    }
+
+    #[test]
+    fn test_explicit_paths_are_left_alone() {
+        let legacy = tempfile::tempdir().unwrap();
+        let target = tempfile::tempdir().unwrap();
+        fs::create_dir(legacy.path().join("work_records")).unwrap();
+        fs::write(legacy.path().join("config.yml"), "").unwrap();
+        let mut paths = Paths {
+            profile: DEFAULT_PROFILE.to_string(),
+            config_file: legacy.path().join("explicit.yml"),
+            data_dir: target.path().to_path_buf(),
+            explicit_config: true,
+            explicit_data_dir: true,
+        };
+
+        paths.migrate_from(legacy.path()).unwrap();
+
+        assert!(legacy.path().join("work_records").is_dir());
+        assert!(!target.path().join("work_records").exists());
+        assert!(!paths.config_file.exists());
+    }
+
+    #[test]
+    fn test_switch_profile() {
+        let paths = Paths {
+            profile: DEFAULT_PROFILE.to_string(),
+            config_file: PathBuf::from("config.yml"),
+            data_dir: PathBuf::from("data"),
+            explicit_config: false,
+            explicit_data_dir: false,
+        };
+        for name in ["../x", "work/../../x", "a\\b", "..", ""] {
+            assert!(paths.of_other_profile(name).is_err(), "{name}");
+        }
+        assert_eq!(paths.of_other_profile("work").unwrap().profile, "work");
+
+        let explicit = Paths {
+            explicit_data_dir: true,
+            ..paths
+        };
+        assert!(explicit.of_other_profile("work").is_err());
+    }
+
+    #[test]
+    fn test_profiles() {
+        let config = tempfile::tempdir().unwrap();
+        let data = tempfile::tempdir().unwrap();
+        fs::create_dir_all(config.path().join("profiles/work")).unwrap();
+        fs::write(config.path().join("profiles/work/config.toml"), "").unwrap();
+        fs::create_dir_all(config.path().join("profiles/without-config")).unwrap();
+
+        assert_eq!(profiles_in(config.path()), vec!["default", "work"]);
+        let default = Paths::of_profile_in(DEFAULT_PROFILE, config.path(), data.path());
+        assert_eq!(default.config_file, config.path().join("config.yml"));
+        assert_eq!(default.data_dir, data.path());
+        let work = Paths::of_profile_in("work", config.path(), data.path());
+        assert_eq!(
+            work.config_file,
+            config.path().join("profiles/work/config.toml")
+        );
+        assert_eq!(work.data_dir, data.path().join("profiles/work"));
+    }
+}
@@ -1,7 +1,9 @@
 use crate::app_config::{AppConfig, Client, ProjectClient};
-use crate::repository::model::WorkRecord;
-use crate::SETTINGS;
-use std::collections::HashMap;
+use crate::repository::model::{TimeKind, WorkRecord};
+use crate::time::{format_local, local_midnight};
+use chrono::{Datelike, Days, NaiveDate, Utc, Weekday};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{Display, Formatter};
 
 #[derive(Debug, Clone)]
 pub struct Report {
@@ -20,6 +22,20 @@ struct Cell {
     value: String,
 }
 
+impl Row {
+    fn new(cells: Vec<(&str, String)>) -> Row {
+        Row {
+            cells: cells
+                .into_iter()
+                .map(|(title, value)| Cell {
+                    title: title.to_string(),
+                    value,
+                })
+                .collect(),
+        }
+    }
+}
+
 impl<'a> From<&Row> for tui::widgets::Row<'a> {
     fn from(value: &Row) -> Self {
         let cells: Vec<tui::widgets::Cell> = value.cells.iter().map(|x| x.into()).collect();
@@ -33,9 +49,33 @@ impl<'a> From<&Cell> for tui::widgets::Cell<'a> {
     }
 }
 
+/// The report as plain text, one row per line.
+impl Display for Report {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for row in &self.rows {
+            let line = row
+                .cells
+                .iter()
+                .map(|cell| format!("{:<20}", cell.value))
+                .collect::<String>();
+            writeln!(f, "{}", line.trim_end())?;
+        }
+        Ok(())
+    }
+}
+
 impl Report {
-    pub fn new_pct(records: &Vec<WorkRecord>) -> Report {
-        let cfg = SETTINGS.read().expect("could not acquire lock");
+    /// Sums up the billable time spent per client between the local days `first_day` and
+    /// `last_day` (inclusive), and the working time that isn't billable, followed by the working
+    /// time of each day.
+    pub fn new_pct(
+        records: &Vec<WorkRecord>,
+        first_day: NaiveDate,
+        last_day: NaiveDate,
+        cfg: &AppConfig,
+    ) -> Report {
+        let from = local_midnight(first_day);
+        let to = local_midnight(last_day + Days::new(1));
         let projects: HashMap<&String, &Vec<ProjectClient>> =
             cfg.projects.iter().map(|p| (&p.name, &p.clients)).collect();
         let clients: HashMap<&String, &Client> = cfg.clients.iter().map(|c| (&c.name, c)).collect();
@@ -45,8 +85,20 @@ impl Report {
             .filter(|c| c.data.contains_key("psp"))
             .map(|c| (&c.data["psp"], 0.))
             .collect();
+        let working = |kind: &TimeKind| cfg.is_working(kind);
+        let mut not_billable = 0.;
 
         for record in records {
+            let duration: f64 = record
+                .calculate_duration_between(from, to, |kind| cfg.is_billable(kind))
+                .num_minutes() as f64;
+            not_billable += record
+                .calculate_duration_between(from, to, working)
+                .num_minutes() as f64
+                - duration;
+            if duration == 0. {
+                continue;
+            }
             let project_clients = *projects.get(&record.name).unwrap_or_else(|| {
                 panic!(
                     "no clients found for project {}, please revise configuration",
@@ -71,7 +123,6 @@ impl Report {
                     record.name
                 )
             }
-            let duration: f64 = record.calculate_duration().num_minutes() as f64;
             for p in project_clients {
                 let client = clients
                     .get(&p.name)
@@ -101,24 +152,199 @@ impl Report {
             .map(|cells| Row { cells })
             .collect();
 
-        Report::sort_rows(&cfg, &mut rows);
-        let sum: f64 = entries.into_values().reduce(|a, b| a + b).unwrap_or(0.);
-        rows.push(Row { cells: vec![] });
-        rows.push(Row {
-            cells: vec![
-                Cell {
-                    title: "Total".to_string(),
-                    value: "Total".to_string(),
-                },
-                Cell {
-                    title: "hours".to_string(),
-                    value: format!("{:.2}h", sum / 60.),
-                },
-            ],
-        });
+        Report::sort_rows(cfg, &mut rows);
+        let sum: f64 = entries.into_values().reduce(|a, b| a + b).unwrap_or(0.) + not_billable;
+        if not_billable > 0. {
+            rows.push(Row::new(vec![
+                ("psp", "not billable".to_string()),
+                ("hours", format!("{:.2}h", not_billable / 60.)),
+            ]));
+        }
+        rows.extend(Report::total_rows(
+            records, sum, first_day, last_day, &working,
+        ));
+        Report { rows }
+    }
+
+    /// Sums up the `working` time spent per tag between the local days `first_day` and `last_day`
+    /// (inclusive), followed by the total of each day. Records with several tags count for each
+    /// of them, so the tags may add up to more than the total.
+    pub fn new_by_tag(
+        records: &[WorkRecord],
+        first_day: NaiveDate,
+        last_day: NaiveDate,
+        working: &dyn Fn(&TimeKind) -> bool,
+    ) -> Report {
+        let from = local_midnight(first_day);
+        let to = local_midnight(last_day + Days::new(1));
+        let mut entries: BTreeMap<&str, f64> = BTreeMap::new();
+        let mut untagged = 0.;
+        let mut sum = 0.;
+        for record in records {
+            let duration = record
+                .calculate_duration_between(from, to, working)
+                .num_minutes() as f64;
+            sum += duration;
+            if record.tags.is_empty() {
+                untagged += duration;
+            }
+            for tag in &record.tags {
+                *entries.entry(tag).or_default() += duration;
+            }
+        }
+
+        let mut rows: Vec<Row> = entries
+            .into_iter()
+            .filter(|(_, minutes)| *minutes > 0.)
+            .map(|(tag, minutes)| {
+                Row::new(vec![
+                    ("tag", tag.to_string()),
+                    ("hours", format!("{:.2}h", minutes / 60.)),
+                ])
+            })
+            .collect();
+        if untagged > 0. {
+            rows.push(Row::new(vec![
+                ("tag", "untagged".to_string()),
+                ("hours", format!("{:.2}h", untagged / 60.)),
+            ]));
+        }
+        rows.extend(Report::total_rows(
+            records, sum, first_day, last_day, working,
+        ));
         Report { rows }
     }
 
+    /// the total of `sum` minutes, followed by the `working` time of the
+    /// [subtotals](Report::subtotals)
+    fn total_rows(
+        records: &[WorkRecord],
+        sum: f64,
+        first_day: NaiveDate,
+        last_day: NaiveDate,
+        working: &dyn Fn(&TimeKind) -> bool,
+    ) -> Vec<Row> {
+        let mut rows = vec![
+            Row { cells: vec![] },
+            Row::new(vec![
+                ("Total", "Total".to_string()),
+                ("hours", format!("{:.2}h", sum / 60.)),
+            ]),
+            Row { cells: vec![] },
+        ];
+        rows.extend(Report::subtotals(first_day, last_day).into_iter().map(
+            |(title, first, last)| {
+                let from = local_midnight(first);
+                let to = local_midnight(last + Days::new(1));
+                let minutes: i64 = records
+                    .iter()
+                    .map(|record| {
+                        record
+                            .calculate_duration_between(from, to, working)
+                            .num_minutes()
+                    })
+                    .sum();
+                Row::new(vec![
+                    ("period", title),
+                    ("hours", format!("{:.2}h", minutes as f64 / 60.)),
+                ])
+            },
+        ));
+        rows
+    }
+
+    /// Lists every record between the local days `first_day` and `last_day` (inclusive) below the
+    /// totals, with the `working` time within the range and its description.
+    pub fn add_details(
+        &mut self,
+        records: &[WorkRecord],
+        first_day: NaiveDate,
+        last_day: NaiveDate,
+        working: &dyn Fn(&TimeKind) -> bool,
+    ) {
+        let from = local_midnight(first_day);
+        let to = local_midnight(last_day + Days::new(1));
+        let mut records: Vec<&WorkRecord> = records.iter().collect();
+        records.sort_by_key(|record| record.start);
+        self.rows.push(Row { cells: vec![] });
+        for record in records {
+            let duration = record.calculate_duration_between(from, to, working);
+            if duration.is_zero() {
+                continue;
+            }
+            self.rows.push(Row::new(vec![
+                ("start", format_local(&record.start)),
+                ("project", record.name.clone()),
+                (
+                    "hours",
+                    format!("{:.2}h", duration.num_minutes() as f64 / 60.),
+                ),
+                (
+                    "description",
+                    record.description.clone().unwrap_or_default(),
+                ),
+            ]));
+        }
+    }
+
+    /// Adds the time paused per reason on each day between the local days `first_day` and
+    /// `last_day` (inclusive).
+    pub fn add_breaks(
+        &mut self,
+        records: &[WorkRecord],
+        first_day: NaiveDate,
+        last_day: NaiveDate,
+    ) {
+        let now = Utc::now();
+        self.rows.push(Row { cells: vec![] });
+        for day in first_day.iter_days().take_while(|day| *day <= last_day) {
+            let from = local_midnight(day);
+            let to = local_midnight(day + Days::new(1));
+            let mut breaks: BTreeMap<&str, chrono::Duration> = BTreeMap::new();
+            for segment in records
+                .iter()
+                .flat_map(|record| &record.segments)
+                .filter(|segment| segment.kind == TimeKind::Pause)
+            {
+                let duration = segment.duration_between(from, to, now);
+                if !duration.is_zero() {
+                    let reason = segment.reason.as_deref().unwrap_or("no reason");
+                    let total = breaks.entry(reason).or_insert_with(chrono::Duration::zero);
+                    *total = *total + duration;
+                }
+            }
+            for (reason, duration) in breaks {
+                self.rows.push(Row::new(vec![
+                    ("day", day.format("%a %Y-%m-%d").to_string()),
+                    ("reason", reason.to_string()),
+                    (
+                        "hours",
+                        format!("{:.2}h", duration.num_minutes() as f64 / 60.),
+                    ),
+                ]));
+            }
+        }
+    }
+
+    /// The periods the total is broken down into: days for up to a week, weeks (cut to the
+    /// range) for anything longer.
+    fn subtotals(first_day: NaiveDate, last_day: NaiveDate) -> Vec<(String, NaiveDate, NaiveDate)> {
+        let days = first_day.iter_days().take_while(|day| *day <= last_day);
+        if last_day.signed_duration_since(first_day).num_days() < 7 {
+            return days
+                .map(|day| (day.format("%a %Y-%m-%d").to_string(), day, day))
+                .collect();
+        }
+        let mut weeks: Vec<(String, NaiveDate, NaiveDate)> = vec![];
+        for day in days {
+            match weeks.last_mut() {
+                Some((_, _, last)) if day.weekday() != Weekday::Mon => *last = day,
+                _ => weeks.push((format!("week {}", day.format("%G/%V")), day, day)),
+            }
+        }
+        weeks
+    }
+
     fn sort_rows(cfg: &AppConfig, rows: &mut [Row]) {
         let mut index: usize = 0;
         let mut sort_order: HashMap<&String, usize> = HashMap::new();
@@ -132,3 +358,194 @@ impl Report {
         rows.sort_by(|a, b| sort_order[&a.cells[0].value].cmp(&sort_order[&b.cells[0].value]));
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, Utc};
+
+    use crate::repository::model::{TimeKind, TimeSegment};
+
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    /// a record of 90 minutes
+    fn record(id: &str, start: DateTime<Utc>, description: Option<&str>) -> WorkRecord {
+        let mut record = WorkRecord::new("EKS".to_string(), Default::default(), start);
+        record.id = id.to_string();
+        record.description = description.map(str::to_string);
+        record.close_at(start + chrono::Duration::minutes(90));
+        record
+    }
+
+    fn values(report: &Report) -> Vec<Vec<&str>> {
+        report
+            .rows
+            .iter()
+            .map(|row| row.cells.iter().map(|cell| cell.value.as_str()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_add_details() {
+        let day = date(2023, 7, 4);
+        let start = crate::time::parse_local("08:00", day).unwrap();
+        let mut report = Report { rows: vec![] };
+
+        report.add_details(
+            &[
+                record("b", start + chrono::Duration::hours(2), None),
+                record("a", start, Some("sprint planning")),
+                record("c", start + chrono::Duration::days(1), None),
+            ],
+            day,
+            day,
+            &TimeKind::is_working_by_default,
+        );
+
+        assert_eq!(
+            values(&report),
+            vec![
+                vec![],
+                vec!["2023-07-04 08:00", "EKS", "1.50h", "sprint planning"],
+                vec!["2023-07-04 10:00", "EKS", "1.50h", ""],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_new_pct_counts_time_that_is_not_billable() {
+        let cfg: AppConfig = config::Config::builder()
+            .add_source(config::File::from_str(
+                "
+projects:
+  - name: EKS
+    clients:
+      - name: Innovation
+clients:
+  - name: Innovation
+    data:
+      psp: IT.2
+kinds:
+  - name: Travel
+    billable: false",
+                config::FileFormat::Yaml,
+            ))
+            .build()
+            .unwrap()
+            .try_deserialize()
+            .unwrap();
+        let day = date(2023, 7, 4);
+        let start = crate::time::parse_local("08:00", day).unwrap();
+        let mut travel = record("b", start + chrono::Duration::hours(2), None);
+        travel.segments[0].kind = TimeKind::Other("Travel".to_string());
+
+        let report = Report::new_pct(&vec![record("a", start, None), travel], day, day, &cfg);
+
+        assert_eq!(
+            values(&report),
+            vec![
+                vec!["IT.2", "1.50h"],
+                vec!["not billable", "1.50h"],
+                vec![],
+                vec!["Total", "3.00h"],
+                vec![],
+                vec!["Tue 2023-07-04", "3.00h"],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_new_by_tag() {
+        let day = date(2023, 7, 4);
+        let start = crate::time::parse_local("08:00", day).unwrap();
+        let mut meeting = record("a", start, None);
+        meeting.tags = ["meeting".to_string(), "support".to_string()].into();
+        let mut support = record("b", start + chrono::Duration::hours(2), None);
+        support.tags = ["support".to_string()].into();
+        let untagged = record("c", start + chrono::Duration::hours(4), None);
+
+        let report = Report::new_by_tag(
+            &[meeting, support, untagged],
+            day,
+            day,
+            &TimeKind::is_working_by_default,
+        );
+
+        assert_eq!(
+            values(&report),
+            vec![
+                vec!["meeting", "1.50h"],
+                vec!["support", "3.00h"],
+                vec!["untagged", "1.50h"],
+                vec![],
+                vec!["Total", "4.50h"],
+                vec![],
+                vec!["Tue 2023-07-04", "4.50h"],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_add_breaks() {
+        let day = date(2023, 7, 4);
+        let start = crate::time::parse_local("08:00", day).unwrap();
+        let minutes = |minutes: i64| start + chrono::Duration::minutes(minutes);
+        let pause = |from: i64, to: i64, reason: Option<&str>| TimeSegment {
+            start: minutes(from),
+            end: Some(minutes(to)),
+            kind: TimeKind::Pause,
+            reason: reason.map(str::to_string),
+            extra: Default::default(),
+        };
+        let mut first = record("a", start, None);
+        first.segments.push(pause(90, 105, Some("coffee")));
+        let mut second = record("b", minutes(120), None);
+        second.segments.push(pause(210, 225, None));
+        let mut report = Report { rows: vec![] };
+
+        report.add_breaks(&[first, second], day, day + Days::new(1));
+
+        assert_eq!(
+            values(&report),
+            vec![
+                vec![],
+                vec!["Tue 2023-07-04", "coffee", "0.25h"],
+                vec!["Tue 2023-07-04", "no reason", "0.25h"],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_subtotals() {
+        let days = Report::subtotals(date(2023, 7, 3), date(2023, 7, 9));
+        assert_eq!(days.len(), 7);
+        assert_eq!(days[0].0, "Mon 2023-07-03");
+
+        let weeks = Report::subtotals(date(2023, 7, 1), date(2023, 7, 31));
+        let titles: Vec<&str> = weeks.iter().map(|(title, _, _)| title.as_str()).collect();
+        assert_eq!(
+            titles,
+            vec![
+                "week 2023/26",
+                "week 2023/27",
+                "week 2023/28",
+                "week 2023/29",
+                "week 2023/30",
+                "week 2023/31"
+            ]
+        );
+        assert_eq!(
+            weeks[0],
+            (
+                "week 2023/26".to_string(),
+                date(2023, 7, 1),
+                date(2023, 7, 2)
+            )
+        );
+        assert_eq!(weeks[5].1, date(2023, 7, 31));
+        assert_eq!(weeks[5].2, date(2023, 7, 31));
+    }
+}
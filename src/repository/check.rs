@@ -0,0 +1,268 @@
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+
+use crate::repository::model::WorkRecord;
+use crate::time::format_local;
+
+/// Something that is wrong with the stored records, see [check].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Problem {
+    /// the record or one of its segments ends before it starts
+    EndBeforeStart(WorkRecord),
+    /// the segments are not sorted by their start
+    SegmentsOutOfOrder(WorkRecord),
+    /// a segment starts before the previous one ended
+    SegmentsOverlap(WorkRecord),
+    /// the record is not stored where it is looked up by its start
+    WrongWeek(WorkRecord),
+    /// only one record can be open at a time
+    MultipleOpen(Vec<WorkRecord>),
+    /// the same time was spent on two records
+    Overlap(WorkRecord, WorkRecord),
+    /// the project of the record is missing in the config
+    UnknownProject(WorkRecord),
+}
+
+impl Display for Problem {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let describe = |record: &WorkRecord| {
+            format!(
+                "{} at {} ({})",
+                record.name,
+                format_local(&record.start),
+                record.id
+            )
+        };
+        match self {
+            Problem::EndBeforeStart(record) => {
+                write!(f, "{} ends before it starts", describe(record))
+            }
+            Problem::SegmentsOutOfOrder(record) => {
+                write!(f, "{} has segments out of order", describe(record))
+            }
+            Problem::SegmentsOverlap(record) => {
+                write!(f, "{} has overlapping segments", describe(record))
+            }
+            Problem::WrongWeek(record) => {
+                write!(f, "{} is stored in the wrong week", describe(record))
+            }
+            Problem::MultipleOpen(records) => {
+                let records: Vec<String> = records.iter().map(describe).collect();
+                write!(f, "several records are open: {}", records.join(", "))
+            }
+            Problem::Overlap(first, second) => {
+                write!(f, "{} overlaps {}", describe(first), describe(second))
+            }
+            Problem::UnknownProject(record) => {
+                write!(
+                    f,
+                    "{} belongs to a project missing in the config",
+                    describe(record)
+                )
+            }
+        }
+    }
+}
+
+/// Looks for inconsistencies in `records`. `misplaced` are the records the storage found in the
+/// wrong place, `projects` the names of all configured projects.
+pub fn check(
+    records: &[WorkRecord],
+    misplaced: &[WorkRecord],
+    projects: &[String],
+) -> Vec<Problem> {
+    let mut problems = vec![];
+    for record in records {
+        if record.end.is_some_and(|end| end < record.start)
+            || record
+                .segments
+                .iter()
+                .any(|segment| segment.end.is_some_and(|end| end < segment.start))
+        {
+            problems.push(Problem::EndBeforeStart(record.clone()));
+        }
+        let pairs = || record.segments.iter().zip(record.segments.iter().skip(1));
+        if pairs().any(|(segment, next)| segment.start > next.start) {
+            problems.push(Problem::SegmentsOutOfOrder(record.clone()));
+        } else if pairs().any(|(segment, next)| segment.end.is_none_or(|end| end > next.start)) {
+            problems.push(Problem::SegmentsOverlap(record.clone()));
+        }
+    }
+    problems.extend(misplaced.iter().cloned().map(Problem::WrongWeek));
+
+    let mut sorted: Vec<&WorkRecord> = records.iter().collect();
+    sorted.sort_by_key(|record| record.start);
+    let open: Vec<WorkRecord> = sorted
+        .iter()
+        .filter(|record| record.end.is_none())
+        .map(|record| (*record).clone())
+        .collect();
+    if open.len() > 1 {
+        problems.push(Problem::MultipleOpen(open));
+    }
+    for (index, record) in sorted.iter().enumerate() {
+        let end = record.end.unwrap_or_else(|| record.last_activity());
+        for other in sorted[index + 1..]
+            .iter()
+            .take_while(|other| other.start < end)
+        {
+            problems.push(Problem::Overlap((*record).clone(), (*other).clone()));
+        }
+    }
+
+    problems.extend(
+        records
+            .iter()
+            .filter(|record| !projects.contains(&record.name))
+            .cloned()
+            .map(Problem::UnknownProject),
+    );
+    problems
+}
+
+/// Fixes the problems that can be fixed without guessing and returns the records to save. Records
+/// with several problems are fixed one after the other. Overlapping records and unknown projects
+/// are left to the user, as well as records whose end can't be told from their segments.
+pub fn repair(problems: &[Problem]) -> Vec<WorkRecord> {
+    let mut repaired: HashMap<String, WorkRecord> = HashMap::new();
+    for problem in problems {
+        match problem {
+            Problem::EndBeforeStart(record) => {
+                let mut record = current(record, &repaired);
+                if record
+                    .segments
+                    .iter()
+                    .any(|segment| segment.end.is_some_and(|end| end < segment.start))
+                {
+                    continue;
+                }
+                let last_end = record.segments.last().and_then(|segment| segment.end);
+                if record.end.is_some() {
+                    match last_end {
+                        Some(end) if end >= record.start => record.end = Some(end),
+                        _ => continue,
+                    }
+                }
+                repaired.insert(record.id.clone(), record);
+            }
+            Problem::SegmentsOutOfOrder(record) | Problem::SegmentsOverlap(record) => {
+                let mut record = current(record, &repaired);
+                record.segments.sort_by_key(|segment| segment.start);
+                let starts: Vec<_> = record.segments.iter().skip(1).map(|s| s.start).collect();
+                for (segment, next_start) in record.segments.iter_mut().zip(starts) {
+                    if segment.end.is_none_or(|end| end > next_start) {
+                        segment.end = Some(next_start);
+                    }
+                }
+                repaired.insert(record.id.clone(), record);
+            }
+            Problem::WrongWeek(record) => {
+                let record = current(record, &repaired);
+                repaired.insert(record.id.clone(), record);
+            }
+            Problem::MultipleOpen(records) => {
+                // the latest one is the one the app continues with
+                for record in &records[..records.len() - 1] {
+                    let mut record = current(record, &repaired);
+                    let end = record.last_activity();
+                    record.close_at(end);
+                    repaired.insert(record.id.clone(), record);
+                }
+            }
+            Problem::Overlap(..) | Problem::UnknownProject(_) => {}
+        }
+    }
+    repaired.into_values().collect()
+}
+
+/// the repaired version of `record`, if there is one
+fn current(record: &WorkRecord, repaired: &HashMap<String, WorkRecord>) -> WorkRecord {
+    repaired
+        .get(&record.id)
+        .cloned()
+        .unwrap_or_else(|| record.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use chrono::{DateTime, Duration, TimeZone, Utc};
+
+    use crate::repository::model::{ProjectState, TimeKind};
+
+    use super::*;
+
+    fn record(id: &str, start: DateTime<Utc>, hours: i64) -> WorkRecord {
+        let mut record = WorkRecord::new("EKS".to_string(), BTreeSet::new(), start);
+        record.id = id.to_string();
+        record
+            .switch_kind(TimeKind::Pause, start + Duration::minutes(30))
+            .unwrap();
+        record.close_at(start + Duration::hours(hours));
+        record
+    }
+
+    fn open(mut record: WorkRecord) -> WorkRecord {
+        record.end = None;
+        record.state = ProjectState::Working;
+        record.segments.last_mut().unwrap().end = None;
+        record
+    }
+
+    #[test]
+    fn test_valid_records() {
+        let start = Utc.with_ymd_and_hms(2023, 7, 4, 8, 0, 0).unwrap();
+        let records = vec![
+            record("a", start, 1),
+            open(record("b", start + Duration::hours(1), 1)),
+        ];
+        assert_eq!(check(&records, &[], &["EKS".to_string()]), vec![]);
+    }
+
+    #[test]
+    fn test_check_and_repair() {
+        let start = Utc.with_ymd_and_hms(2023, 7, 4, 8, 0, 0).unwrap();
+        let mut backwards = record("backwards", start, 1);
+        backwards.end = Some(start - Duration::hours(1));
+        let mut shuffled = record("shuffled", start + Duration::hours(2), 1);
+        shuffled.segments.reverse();
+        let inside = record("inside", start + Duration::minutes(150), 1);
+        let mut overlapping = record("overlapping", start + Duration::hours(4), 1);
+        overlapping.segments[0].end = Some(start + Duration::hours(5));
+        let first_open = open(record("first open", start + Duration::hours(6), 1));
+        let second_open = open(record("second open", start + Duration::hours(8), 1));
+        let mut unknown = record("unknown", start + Duration::days(1), 1);
+        unknown.name = "Gone".to_string();
+        let records = vec![
+            backwards.clone(),
+            shuffled.clone(),
+            inside.clone(),
+            overlapping.clone(),
+            first_open.clone(),
+            second_open.clone(),
+            unknown.clone(),
+        ];
+
+        let problems = check(&records, &[], &["EKS".to_string()]);
+        assert_eq!(
+            problems,
+            vec![
+                Problem::EndBeforeStart(backwards),
+                Problem::SegmentsOutOfOrder(shuffled.clone()),
+                Problem::SegmentsOverlap(overlapping),
+                Problem::MultipleOpen(vec![first_open, second_open]),
+                Problem::Overlap(shuffled, inside),
+                Problem::UnknownProject(unknown),
+            ]
+        );
+
+        let repaired = repair(&problems);
+        assert_eq!(repaired.len(), 4);
+        assert_eq!(
+            check(&repaired, &[], &["EKS".to_string()]),
+            vec![],
+            "{repaired:#?}"
+        );
+    }
+}
@@ -0,0 +1,71 @@
+use std::fmt::{Display, Formatter};
+use std::io;
+use std::path::PathBuf;
+
+pub type Result<T> = std::result::Result<T, RepositoryError>;
+
+#[derive(Debug)]
+pub enum RepositoryError {
+    Io(io::Error),
+    /// a file of the database could not be parsed and was moved to `backup`
+    Corrupt {
+        path: PathBuf,
+        backup: PathBuf,
+        source: serde_json::Error,
+    },
+    Sqlite(rusqlite::Error),
+    /// a file of the database was written by a newer version of track-work
+    Unsupported {
+        path: PathBuf,
+        version: u32,
+    },
+    /// the requested change would leave the records inconsistent
+    Invalid(String),
+}
+
+impl Display for RepositoryError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RepositoryError::Io(e) => Display::fmt(e, f),
+            RepositoryError::Sqlite(e) => Display::fmt(e, f),
+            RepositoryError::Invalid(reason) => f.write_str(reason),
+            RepositoryError::Unsupported { path, version } => write!(
+                f,
+                "{} was written by a newer version of track-work (schema version {}), please update",
+                path.display(),
+                version
+            ),
+            RepositoryError::Corrupt {
+                path,
+                backup,
+                source,
+            } => write!(
+                f,
+                "{} is corrupt ({}), it was moved to {}",
+                path.display(),
+                source,
+                backup.display()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for RepositoryError {}
+
+impl From<io::Error> for RepositoryError {
+    fn from(value: io::Error) -> Self {
+        RepositoryError::Io(value)
+    }
+}
+
+impl From<serde_json::Error> for RepositoryError {
+    fn from(value: serde_json::Error) -> Self {
+        RepositoryError::Io(value.into())
+    }
+}
+
+impl From<rusqlite::Error> for RepositoryError {
+    fn from(value: rusqlite::Error) -> Self {
+        RepositoryError::Sqlite(value)
+    }
+}
@@ -0,0 +1,187 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::repository::error::Result;
+use crate::repository::model::WorkRecord;
+
+const INDEX: &str = ".index";
+
+/// What a week file contains, so range queries can skip it without reading it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct IndexEntry {
+    /// the entry is outdated once the file was changed
+    modified: SystemTime,
+    len: u64,
+    /// the earliest start, `None` if the file contains no records
+    first_start: Option<DateTime<Utc>>,
+    /// the latest end, `None` if the file contains a record that is still open
+    last_end: Option<DateTime<Utc>>,
+    projects: BTreeSet<String>,
+}
+
+impl IndexEntry {
+    fn new(modified: SystemTime, len: u64, records: &HashMap<String, WorkRecord>) -> IndexEntry {
+        let records = records.values();
+        IndexEntry {
+            modified,
+            len,
+            first_start: records.clone().map(|record| record.start).min(),
+            last_end: records
+                .clone()
+                .map(|record| record.end)
+                .try_fold(DateTime::<Utc>::MIN_UTC, |last, end| Some(last.max(end?))),
+            projects: records.map(|record| record.name.clone()).collect(),
+        }
+    }
+
+    fn may_contain(&self, from: DateTime<Utc>, to: DateTime<Utc>, project: Option<&str>) -> bool {
+        let Some(first_start) = self.first_start else {
+            return false;
+        };
+        first_start < to
+            && self.last_end.is_none_or(|last_end| last_end > from)
+            && project.is_none_or(|project| self.projects.contains(project))
+    }
+}
+
+/// A summary of all week files in `work_records/.index`. It is only a cache: entries of changed
+/// files are rebuilt when they are needed, and a missing or broken index is simply rebuilt.
+#[derive(Debug)]
+pub struct Index {
+    path: PathBuf,
+    entries: BTreeMap<String, IndexEntry>,
+    changed: bool,
+}
+
+impl Index {
+    pub fn load(folder: &Path) -> Index {
+        let path = folder.join(INDEX);
+        let entries = fs::read(&path)
+            .ok()
+            .and_then(|content| serde_json::from_slice(&content).ok())
+            .unwrap_or_default();
+        Index {
+            path,
+            entries,
+            changed: false,
+        }
+    }
+
+    /// The records of the week file at `path`, or `None` if it can't contain records that overlap
+    /// `from` to `to`. `load` reads the file at most once, also in case its entry is outdated.
+    pub fn read_if_overlapping(
+        &mut self,
+        path: &PathBuf,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        project: Option<&str>,
+        load: impl Fn(&PathBuf) -> Result<HashMap<String, WorkRecord>>,
+    ) -> Result<Option<HashMap<String, WorkRecord>>> {
+        let metadata = fs::metadata(path)?;
+        let modified = metadata.modified()?;
+        let name = path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+        let outdated = self
+            .entries
+            .get(&name)
+            .is_none_or(|entry| entry.modified != modified || entry.len != metadata.len());
+        let mut records = None;
+        if outdated {
+            let loaded = load(path)?;
+            let entry = IndexEntry::new(modified, metadata.len(), &loaded);
+            self.entries.insert(name.clone(), entry);
+            self.changed = true;
+            records = Some(loaded);
+        }
+        if !self.entries[&name].may_contain(from, to, project) {
+            return Ok(None);
+        }
+        match records {
+            Some(records) => Ok(Some(records)),
+            None => load(path).map(Some),
+        }
+    }
+
+    /// Saves the index if anything changed, forgetting all files but `files`.
+    pub fn save(mut self, files: &[PathBuf]) -> Result<()> {
+        let names: BTreeSet<String> = files
+            .iter()
+            .map(|path| {
+                path.file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .to_string()
+            })
+            .collect();
+        let count = self.entries.len();
+        self.entries.retain(|name, _| names.contains(name));
+        if !self.changed && count == self.entries.len() {
+            return Ok(());
+        }
+        let temp_path = self.path.with_extension("tmp");
+        fs::write(&temp_path, serde_json::to_vec(&self.entries)?)?;
+        fs::rename(&temp_path, &self.path)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, TimeZone};
+
+    use super::*;
+
+    fn records(starts: &[(DateTime<Utc>, bool)]) -> HashMap<String, WorkRecord> {
+        starts
+            .iter()
+            .enumerate()
+            .map(|(id, (start, open))| {
+                let mut record = WorkRecord::new("EKS".to_string(), BTreeSet::new(), *start);
+                record.id = id.to_string();
+                if !open {
+                    record.close_at(*start + Duration::hours(1));
+                }
+                record
+            })
+            .map(|record| (record.id.clone(), record))
+            .collect()
+    }
+
+    #[test]
+    fn test_may_contain() {
+        let monday = Utc.with_ymd_and_hms(2023, 7, 3, 8, 0, 0).unwrap();
+        let friday = Utc.with_ymd_and_hms(2023, 7, 7, 8, 0, 0).unwrap();
+        let entry = IndexEntry::new(
+            SystemTime::UNIX_EPOCH,
+            0,
+            &records(&[(monday, false), (friday, false)]),
+        );
+        assert!(entry.may_contain(monday, friday, None));
+        assert!(entry.may_contain(friday, friday + Duration::days(1), Some("EKS")));
+        assert!(!entry.may_contain(friday, friday + Duration::days(1), Some("Swag")));
+        assert!(!entry.may_contain(
+            friday + Duration::hours(1),
+            friday + Duration::days(1),
+            None
+        ));
+        assert!(!entry.may_contain(monday - Duration::days(1), monday, None));
+
+        let open = IndexEntry::new(SystemTime::UNIX_EPOCH, 0, &records(&[(monday, true)]));
+        assert!(open.may_contain(
+            monday + Duration::days(30),
+            monday + Duration::days(31),
+            None
+        ));
+
+        let empty = IndexEntry::new(SystemTime::UNIX_EPOCH, 0, &HashMap::new());
+        assert!(!empty.may_contain(DateTime::<Utc>::MIN_UTC, DateTime::<Utc>::MAX_UTC, None));
+    }
+}
@@ -0,0 +1,453 @@
+use std::collections::HashMap;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+use std::{fs, io};
+
+use chrono::{DateTime, Local, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::log::log;
+use crate::repository::error::{RepositoryError, Result};
+use crate::repository::model::{Extra, ProjectState, TimeKind, TimeSegment, WorkRecord};
+use crate::repository::schema;
+
+const CURRENT: &str = "current.jsonl";
+
+/// A change to a single work record. Transitions of records in the journal only store the
+/// time they happened at, everything else stores the whole record.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(tag = "event", rename_all = "lowercase")]
+pub enum Event {
+    Started {
+        record: WorkRecord,
+    },
+    Paused {
+        id: String,
+        at: DateTime<Utc>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        reason: Option<String>,
+    },
+    Resumed {
+        id: String,
+        at: DateTime<Utc>,
+    },
+    Stopped {
+        id: String,
+        at: DateTime<Utc>,
+    },
+    Seen {
+        id: String,
+        at: DateTime<Utc>,
+    },
+    Edited {
+        record: WorkRecord,
+    },
+    Deleted {
+        id: String,
+        start: DateTime<Utc>,
+    },
+}
+
+impl Event {
+    fn id(&self) -> &str {
+        match self {
+            Event::Started { record } | Event::Edited { record } => &record.id,
+            Event::Paused { id, .. }
+            | Event::Resumed { id, .. }
+            | Event::Stopped { id, .. }
+            | Event::Seen { id, .. }
+            | Event::Deleted { id, .. } => id,
+        }
+    }
+
+    /// The transitions that could have turned some record into `record`.
+    fn transitions_to(record: &WorkRecord) -> Vec<Event> {
+        let id = record.id.clone();
+        let mut candidates = vec![];
+        // switching to any other kind is stored as an edit
+        match record.segments.last() {
+            Some(last) if last.kind == TimeKind::Pause => candidates.push(Event::Paused {
+                id: id.clone(),
+                at: last.start,
+                reason: last.reason.clone(),
+            }),
+            Some(last) if last.kind == TimeKind::Productive => candidates.push(Event::Resumed {
+                id: id.clone(),
+                at: last.start,
+            }),
+            _ => {}
+        }
+        if let Some(at) = record.end {
+            candidates.push(Event::Stopped { id: id.clone(), at });
+        }
+        if let Some(at) = record.last_seen {
+            candidates.push(Event::Seen { id, at });
+        }
+        candidates
+    }
+
+    /// Applies a transition to `record`, `None` for events that replace the whole record.
+    fn transition(&self, mut record: WorkRecord) -> Option<WorkRecord> {
+        match self {
+            Event::Paused { at, reason, .. } => {
+                let mut record = switch(record, TimeKind::Pause, *at);
+                if let Some(segment) = record.segments.last_mut() {
+                    segment.reason = reason.clone();
+                }
+                Some(record)
+            }
+            Event::Resumed { at, .. } => Some(switch(record, TimeKind::Productive, *at)),
+            Event::Stopped { at, .. } => {
+                if let Some(last_segment) = record.segments.last_mut() {
+                    last_segment.finish_at(*at);
+                    record.end = last_segment.end;
+                }
+                record.state = ProjectState::Done;
+                Some(record)
+            }
+            Event::Seen { at, .. } => {
+                record.last_seen = Some(*at);
+                Some(record)
+            }
+            Event::Started { .. } | Event::Edited { .. } | Event::Deleted { .. } => None,
+        }
+    }
+}
+
+fn switch(mut record: WorkRecord, kind: TimeKind, at: DateTime<Utc>) -> WorkRecord {
+    if let Some(last_segment) = record.segments.last_mut() {
+        last_segment.finish_at(at);
+    }
+    record.state = match kind {
+        TimeKind::Pause => ProjectState::Paused,
+        _ => ProjectState::Working,
+    };
+    record.segments.push(TimeSegment {
+        start: at,
+        end: None,
+        kind,
+        reason: None,
+        extra: Extra::new(),
+    });
+    record
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Entry {
+    /// the [schema version](schema::VERSION) of the record in the event
+    #[serde(default = "schema::unversioned")]
+    version: u32,
+    time: DateTime<Utc>,
+    #[serde(flatten)]
+    event: Event,
+}
+
+/// The state of a record after replaying the journal.
+#[derive(Debug, Clone)]
+pub enum Change {
+    Saved(WorkRecord),
+    Deleted,
+}
+
+/// All records changed since the last snapshot.
+#[derive(Debug, Default)]
+pub struct Replay {
+    pub changes: HashMap<String, Change>,
+    pub events: usize,
+    complete_line: bool,
+}
+
+impl Replay {
+    pub fn record(&self, id: &str) -> Option<&WorkRecord> {
+        match self.changes.get(id) {
+            Some(Change::Saved(record)) => Some(record),
+            _ => None,
+        }
+    }
+
+    pub fn records(&self) -> impl Iterator<Item = &WorkRecord> {
+        self.changes.values().filter_map(|change| match change {
+            Change::Saved(record) => Some(record),
+            Change::Deleted => None,
+        })
+    }
+
+    /// The event that turns the current version of the record into `record`, `None` if nothing
+    /// changed. `stored` is the version in the snapshot, if the journal doesn't know the record.
+    /// A record deleted in the journal is saved again, even if the snapshot still contains it.
+    pub fn event_for(&self, stored: Option<&WorkRecord>, record: WorkRecord) -> Option<Event> {
+        if let Some(Change::Deleted) = self.changes.get(&record.id) {
+            return Some(match stored {
+                Some(_) => Event::Edited { record },
+                None => Event::Started { record },
+            });
+        }
+        let Some(previous) = self.record(&record.id) else {
+            return match stored {
+                Some(stored) if *stored == record => None,
+                Some(_) => Some(Event::Edited { record }),
+                None => Some(Event::Started { record }),
+            };
+        };
+        if *previous == record {
+            return None;
+        }
+        Event::transitions_to(&record)
+            .into_iter()
+            .find(|event| event.transition(previous.clone()).as_ref() == Some(&record))
+            .or(Some(Event::Edited { record }))
+    }
+
+    fn apply(&mut self, event: Event) {
+        let id = event.id().to_string();
+        let change = match event {
+            Event::Started { record } | Event::Edited { record } => Change::Saved(record),
+            Event::Deleted { .. } => Change::Deleted,
+            transition => {
+                let Some(record) = self.record(&id).cloned() else {
+                    log!("⚠ journal: skipping {:?} of an unknown record", transition);
+                    return;
+                };
+                match transition.transition(record) {
+                    Some(record) => Change::Saved(record),
+                    None => return,
+                }
+            }
+        };
+        self.changes.insert(id, change);
+    }
+}
+
+/// An append-only log of all changes to work records. Appending a line is much cheaper than
+/// rewriting a whole week, and a crash can at most cut off the line being written. The records
+/// are written to the week files once in a while, the journal is archived after that.
+#[derive(Debug)]
+pub struct Journal {
+    folder: PathBuf,
+}
+
+impl Journal {
+    pub fn new(folder: PathBuf) -> io::Result<Journal> {
+        fs::create_dir_all(&folder)?;
+        Ok(Journal { folder })
+    }
+
+    /// The journal in `folder`, which may not exist. Only replaying it doesn't change anything.
+    pub fn read_only(folder: PathBuf) -> Journal {
+        Journal { folder }
+    }
+
+    fn current(&self) -> PathBuf {
+        self.folder.join(CURRENT)
+    }
+
+    /// Reads the journal. Lines that can't be parsed, like one cut off by a crash, are skipped,
+    /// while a line written by a newer version of track-work fails the whole journal.
+    pub fn replay(&self) -> Result<Replay> {
+        let mut replay = Replay {
+            complete_line: true,
+            ..Replay::default()
+        };
+        let content = match fs::read_to_string(self.current()) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(replay),
+            Err(e) => return Err(e.into()),
+        };
+        replay.complete_line = content.is_empty() || content.ends_with('\n');
+        for (number, line) in content.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str::<Entry>(line) {
+                Ok(entry) if entry.version > schema::VERSION => {
+                    return Err(RepositoryError::Unsupported {
+                        path: self.current(),
+                        version: entry.version,
+                    });
+                }
+                Ok(entry) => {
+                    replay.events += 1;
+                    replay.apply(entry.event);
+                }
+                Err(e) => log!("⚠ journal: skipping line {}: {}", number + 1, e),
+            }
+        }
+        Ok(replay)
+    }
+
+    /// Appends `event` and waits until it is on disk.
+    pub fn append(&self, replay: &mut Replay, event: Event) -> Result<()> {
+        let mut line = if replay.complete_line {
+            String::new()
+        } else {
+            // starts a new line after a line that was cut off
+            String::from("\n")
+        };
+        let entry = Entry {
+            version: schema::VERSION,
+            time: Utc::now(),
+            event,
+        };
+        line.push_str(&serde_json::to_string(&entry)?);
+        line.push('\n');
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.current())?;
+        file.write_all(line.as_bytes())?;
+        file.sync_data()?;
+        replay.complete_line = true;
+        replay.events += 1;
+        replay.apply(entry.event);
+        Ok(())
+    }
+
+    /// Moves the current journal to `<timestamp>.jsonl` and starts a new one.
+    pub fn archive(&self) -> io::Result<()> {
+        let current = self.current();
+        if !current.exists() {
+            return Ok(());
+        }
+        let name = format!("{}.jsonl", Local::now().format("%Y%m%dT%H%M%S%.3f"));
+        fs::rename(&current, self.folder.join(name))?;
+        #[cfg(unix)]
+        fs::File::open(&self.folder)?.sync_all()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use chrono::{Duration, TimeZone};
+
+    use super::*;
+
+    fn working_record(start: DateTime<Utc>) -> WorkRecord {
+        let mut record = WorkRecord::new("EKS".to_string(), BTreeSet::new(), start);
+        record.id = "a".to_string();
+        record
+    }
+
+    #[test]
+    fn test_transitions_are_journaled() {
+        let directory = tempfile::tempdir().unwrap();
+        let journal = Journal::new(directory.path().to_path_buf()).unwrap();
+        let mut replay = journal.replay().unwrap();
+        let start = Utc.with_ymd_and_hms(2023, 7, 4, 8, 0, 0).unwrap();
+
+        let mut record = working_record(start);
+        let mut versions = vec![record.clone()];
+        record = switch(record, TimeKind::Pause, start + Duration::hours(1));
+        record.segments[1].reason = Some("lunch".to_string());
+        versions.push(record.clone());
+        record.last_seen = Some(start + Duration::minutes(70));
+        versions.push(record.clone());
+        record = switch(record, TimeKind::Productive, start + Duration::hours(2));
+        versions.push(record.clone());
+        record.segments[0].start = start - Duration::minutes(5);
+        versions.push(record.clone());
+
+        let mut events = vec![];
+        for version in versions {
+            let event = replay.event_for(None, version).unwrap();
+            events.push(
+                format!("{:?}", event)
+                    .split(' ')
+                    .next()
+                    .unwrap()
+                    .to_string(),
+            );
+            journal.append(&mut replay, event).unwrap();
+        }
+        assert_eq!(
+            events,
+            vec!["Started", "Paused", "Seen", "Resumed", "Edited"]
+        );
+        assert_eq!(replay.event_for(None, record.clone()), None);
+
+        let replay = journal.replay().unwrap();
+        assert_eq!(replay.events, 5);
+        assert_eq!(replay.record("a"), Some(&record));
+    }
+
+    #[test]
+    fn test_deleted_record_is_saved_again() {
+        let directory = tempfile::tempdir().unwrap();
+        let journal = Journal::new(directory.path().to_path_buf()).unwrap();
+        let mut replay = journal.replay().unwrap();
+        let start = Utc.with_ymd_and_hms(2023, 7, 4, 8, 0, 0).unwrap();
+        let record = working_record(start);
+        let deleted = Event::Deleted {
+            id: "a".to_string(),
+            start,
+        };
+        journal.append(&mut replay, deleted).unwrap();
+
+        // the snapshot still contains the record as it was before the deletion
+        let event = replay.event_for(Some(&record), record.clone());
+        assert_eq!(
+            event,
+            Some(Event::Edited {
+                record: record.clone()
+            })
+        );
+        journal.append(&mut replay, event.unwrap()).unwrap();
+        assert_eq!(journal.replay().unwrap().record("a"), Some(&record));
+    }
+
+    #[test]
+    fn test_versions_of_entries() {
+        let directory = tempfile::tempdir().unwrap();
+        let journal = Journal::new(directory.path().to_path_buf()).unwrap();
+        let record = working_record(Utc.with_ymd_and_hms(2023, 7, 4, 8, 0, 0).unwrap());
+        // written before entries had a version
+        let line = format!(
+            r#"{{"time":"2023-07-04T08:00:00Z","event":"started","record":{}}}"#,
+            serde_json::to_string(&record).unwrap()
+        );
+        fs::write(journal.current(), line + "\n").unwrap();
+        assert_eq!(journal.replay().unwrap().record("a"), Some(&record));
+
+        let mut file = OpenOptions::new()
+            .append(true)
+            .open(journal.current())
+            .unwrap();
+        let stopped = r#"{"version":99,"time":"2023-07-04T09:00:00Z","event":"stopped","id":"a","at":"2023-07-04T09:00:00Z"}"#;
+        writeln!(file, "{stopped}").unwrap();
+        assert!(matches!(
+            journal.replay(),
+            Err(RepositoryError::Unsupported { version: 99, .. })
+        ));
+    }
+
+    #[test]
+    fn test_cut_off_line_is_skipped() {
+        let directory = tempfile::tempdir().unwrap();
+        let journal = Journal::new(directory.path().to_path_buf()).unwrap();
+        let start = Utc.with_ymd_and_hms(2023, 7, 4, 8, 0, 0).unwrap();
+        let mut replay = journal.replay().unwrap();
+        let event = replay.event_for(None, working_record(start)).unwrap();
+        journal.append(&mut replay, event).unwrap();
+        let mut file = OpenOptions::new()
+            .append(true)
+            .open(journal.current())
+            .unwrap();
+        file.write_all(b"{\"time\":\"2023-07-04T09:00:00Z\",\"eve")
+            .unwrap();
+
+        let mut replay = journal.replay().unwrap();
+        assert_eq!(replay.events, 1);
+        let event = Event::Stopped {
+            id: "a".to_string(),
+            at: start + Duration::hours(1),
+        };
+        journal.append(&mut replay, event).unwrap();
+
+        let replay = journal.replay().unwrap();
+        assert_eq!(replay.events, 2);
+        assert_eq!(replay.record("a").unwrap().state, ProjectState::Done);
+    }
+}
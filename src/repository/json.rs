@@ -0,0 +1,951 @@
+use std::collections::{BTreeSet, HashMap};
+use std::fs::{DirEntry, File, OpenOptions};
+use std::io::{BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::{fs, io};
+
+use chrono::{DateTime, Local, Utc};
+use fs2::FileExt;
+
+use crate::log::log;
+use crate::repository::error::{RepositoryError, Result};
+use crate::repository::index::Index;
+use crate::repository::journal::{Change, Event, Journal, Replay};
+use crate::repository::model::WorkRecord;
+use crate::repository::schema::{self, SchemaError};
+use crate::repository::week::Week;
+use crate::repository::work_record::{later_parts, split_into_days, WorkRecordRepository};
+
+/// marks a database whose files are named after ISO weeks, see
+/// [JsonRepository::migrate_week_keys]
+const ISO_WEEKS_MARKER: &str = ".iso-weeks";
+
+/// number of journal entries after which they are written to the week files
+const SNAPSHOT_AFTER: usize = 200;
+
+/// the schema version and the records of a week file
+type WeekFile = (u32, HashMap<String, WorkRecord>);
+
+/// Stores work records in one file per ISO week. Changes are appended to a [Journal] first and
+/// written to the week files every [SNAPSHOT_AFTER] entries, reads combine both.
+#[derive(Debug)]
+pub struct JsonRepository {
+    subfolder: PathBuf,
+    journal: Journal,
+}
+
+impl JsonRepository {
+    pub fn new(path: &Path) -> Result<JsonRepository> {
+        fs::create_dir_all(path)?;
+        let subfolder = path.join("work_records");
+        create_dir_if_not_exists(&subfolder)?;
+        let journal = Journal::new(subfolder.join("journal"))?;
+        let repository = JsonRepository { subfolder, journal };
+        repository.migrate_week_keys()?;
+        Ok(repository)
+    }
+
+    /// Reads every record of the database in `path` without changing anything there, i.e.
+    /// without migrating it or quarantining corrupt files. Used to import another database.
+    pub fn read_all(path: &Path) -> Result<Vec<WorkRecord>> {
+        let folder = path.join("work_records");
+        // waits for changes in progress, if the database was ever locked
+        let _lock = match File::open(folder.join(".lock")) {
+            Ok(file) => {
+                file.lock_shared()?;
+                Some(file)
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e.into()),
+        };
+        let replay = Journal::read_only(folder.join("journal")).replay()?;
+        let mut records: HashMap<String, WorkRecord> = HashMap::new();
+        for path in week_files_in(&folder)? {
+            match JsonRepository::parse_week_file(&path)? {
+                Ok((_, entries)) => records.extend(entries),
+                Err(source) => {
+                    let message = format!("{} is corrupt: {}", path.display(), source);
+                    return Err(io::Error::new(io::ErrorKind::InvalidData, message).into());
+                }
+            }
+        }
+        apply_changes(&mut records, replay);
+        Ok(records.into_values().collect())
+    }
+
+    /// Week files used to be named after the week of the year starting on sunday, while reports
+    /// looked them up by ISO week. Re-buckets all records into files named after the ISO week of
+    /// their start. The previous files are kept in a backup folder.
+    fn migrate_week_keys(&self) -> Result<()> {
+        let marker = self.subfolder.join(ISO_WEEKS_MARKER);
+        if marker.exists() {
+            return Ok(());
+        }
+        let _lock = self.lock_exclusive()?;
+        let files = self.week_files()?;
+        if !files.is_empty() {
+            let backup = self.backup(&files)?;
+            let mut weeks: HashMap<PathBuf, HashMap<String, WorkRecord>> = HashMap::new();
+            for file in &files {
+                match JsonRepository::get_all_of_file(file) {
+                    Ok(entries) => {
+                        for record in entries.into_values() {
+                            weeks
+                                .entry(self.path_of_week(&record.start))
+                                .or_default()
+                                .insert(record.id.clone(), record);
+                        }
+                    }
+                    Err(e) => log!("⚠ {}", e),
+                }
+            }
+            for (path, entries) in &weeks {
+                write_atomically(path, entries)?;
+            }
+            for file in files.iter().filter(|file| !weeks.contains_key(*file)) {
+                match fs::remove_file(file) {
+                    // corrupt files were quarantined already
+                    Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e.into()),
+                    _ => {}
+                }
+            }
+            log!(
+                "migrated {} week files to ISO weeks, the previous files are kept in {}",
+                files.len(),
+                backup.display()
+            );
+        }
+        fs::write(marker, "")?;
+        Ok(())
+    }
+
+    /// Loads the latest week file that contains any records which weren't changed in the journal
+    /// since. Corrupt weeks are skipped, so the app can still start.
+    fn load_latest_week(
+        &self,
+        files: &[PathBuf],
+        replay: &Replay,
+    ) -> Option<HashMap<String, WorkRecord>> {
+        files
+            .iter()
+            .rev()
+            .find_map(|path| match JsonRepository::get_all_of_file(path) {
+                Ok(mut entries) => {
+                    entries.retain(|id, _| !replay.changes.contains_key(id));
+                    Some(entries).filter(|entries| !entries.is_empty())
+                }
+                Err(e) => {
+                    log!("⚠ failed to load {:?}: {}", path, e);
+                    None
+                }
+            })
+    }
+
+    /// Applies the changes in the journal to the records of the week file at `path`.
+    fn overlay(&self, path: &Path, entries: &mut HashMap<String, WorkRecord>, replay: &Replay) {
+        for (id, change) in &replay.changes {
+            match change {
+                Change::Saved(record) if self.path_of_week(&record.start) == path => {
+                    entries.insert(id.clone(), record.clone());
+                }
+                // deleted, or moved to another week
+                _ => {
+                    entries.remove(id);
+                }
+            }
+        }
+    }
+
+    /// all week files of the database, sorted from oldest to newest
+    fn week_files(&self) -> io::Result<Vec<PathBuf>> {
+        week_files_in(&self.subfolder)
+    }
+
+    #[allow(dead_code)]
+    pub fn get_by_id(&self, id: &str, date: &dyn Week) -> Option<WorkRecord> {
+        self.find_week(date)
+            .ok()
+            .and_then(|records| records.into_iter().find(|record| record.id == id))
+    }
+
+    /// Copies `files` to a new `backup-<timestamp>` folder.
+    fn backup(&self, files: &[PathBuf]) -> io::Result<PathBuf> {
+        let backup = self
+            .subfolder
+            .join(format!("backup-{}", Local::now().format("%Y%m%dT%H%M%S")));
+        fs::create_dir(&backup)?;
+        for file in files {
+            fs::copy(file, backup.join(file.file_name().unwrap_or_default()))?;
+        }
+        Ok(backup)
+    }
+
+    /// Writes all changes of the journal to the week files right away.
+    pub fn compact(&self) -> Result<()> {
+        let _lock = self.lock_exclusive()?;
+        let replay = self.journal.replay()?;
+        self.snapshot(&replay)
+    }
+
+    /// The version of `record` in its week file.
+    fn stored(&self, id: &str, start: &DateTime<Utc>) -> Result<Option<WorkRecord>> {
+        let path = self.path_of_week(start);
+        match JsonRepository::get_all_of_file(&path) {
+            Ok(mut entries) => Ok(entries.remove(id)),
+            // the corrupt file is kept as backup, so the week can be continued in a new file
+            Err(e @ RepositoryError::Corrupt { .. }) => {
+                log!("⚠ {}", e);
+                Ok(None)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Writes the changes of the journal to the week files and archives the journal. Writing the
+    /// same journal twice yields the same files, so a crash in between is harmless.
+    fn snapshot(&self, replay: &Replay) -> Result<()> {
+        let mut paths: BTreeSet<PathBuf> = self.week_files()?.into_iter().collect();
+        paths.extend(
+            replay
+                .records()
+                .map(|record| self.path_of_week(&record.start)),
+        );
+        for path in paths {
+            let mut entries = match JsonRepository::get_all_of_file(&path) {
+                Ok(entries) => entries,
+                Err(e @ RepositoryError::Corrupt { .. }) => {
+                    log!("⚠ {}", e);
+                    HashMap::new()
+                }
+                Err(e) => return Err(e),
+            };
+            let stored = entries.clone();
+            self.overlay(&path, &mut entries, replay);
+            if entries != stored {
+                write_atomically(&path, &entries)?;
+            }
+        }
+        self.journal.archive()?;
+        Ok(())
+    }
+
+    /// Locks the whole database against modifications by other processes, until the returned file
+    /// is dropped.
+    fn lock_exclusive(&self) -> io::Result<File> {
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(self.subfolder.join(".lock"))?;
+        file.lock_exclusive()?;
+        Ok(file)
+    }
+
+    fn get_all_of_file(path: &PathBuf) -> Result<HashMap<String, WorkRecord>> {
+        JsonRepository::read_week_file(path).map(|(_, entries)| entries)
+    }
+
+    /// Reads all records of a week file of any schema version, together with that version.
+    /// Files that can't be parsed are moved out of the way, so they are reported only once and
+    /// don't stand in the way of new records.
+    fn read_week_file(path: &PathBuf) -> Result<WeekFile> {
+        let source = match JsonRepository::parse_week_file(path)? {
+            Ok(read) => return Ok(read),
+            Err(source) => source,
+        };
+        let backup = quarantine(path)?;
+        Err(RepositoryError::Corrupt {
+            path: path.clone(),
+            backup,
+            source,
+        })
+    }
+
+    /// Reads a week file like [JsonRepository::read_week_file], a corrupt file is left as it is
+    /// and its parse error returned.
+    fn parse_week_file(path: &PathBuf) -> Result<std::result::Result<WeekFile, serde_json::Error>> {
+        if !path.is_file() {
+            return Ok(Ok((schema::VERSION, HashMap::new())));
+        }
+        let file = File::open(path)?;
+        match serde_json::from_reader(BufReader::new(file)) {
+            Ok(file) => match schema::read(file) {
+                Ok(read) => Ok(Ok(read)),
+                Err(SchemaError::Unsupported(version)) => Err(RepositoryError::Unsupported {
+                    path: path.clone(),
+                    version,
+                }),
+                Err(SchemaError::Invalid(source)) => Ok(Err(source)),
+            },
+            Err(source) if source.is_io() => Err(source.into()),
+            Err(source) => Ok(Err(source)),
+        }
+    }
+
+    fn path_of_week(&self, date: &dyn Week) -> PathBuf {
+        let filename = format!("{}.json", date.to_week()); // 2023-52.json
+        self.subfolder.join(filename)
+    }
+}
+
+impl WorkRecordRepository for JsonRepository {
+    fn get_latest(&self) -> Option<WorkRecord> {
+        let loaded = self
+            .lock_exclusive()
+            .map_err(RepositoryError::from)
+            .and_then(|_lock| Ok((self.journal.replay()?, self.week_files()?)));
+        let (replay, files) = match loaded {
+            Ok(loaded) => loaded,
+            Err(err) => {
+                log!(
+                    "failed to load the database ({}): {}",
+                    self.subfolder.display(),
+                    err
+                );
+                return None;
+            }
+        };
+        let journaled = replay.records().max_by_key(|record| record.start).cloned();
+        let stored = self
+            .load_latest_week(&files, &replay)
+            .and_then(|entries| entries.into_values().max_by_key(|record| record.start));
+        match (journaled, stored) {
+            (Some(journaled), Some(stored)) if stored.start > journaled.start => Some(stored),
+            (Some(journaled), _) => Some(journaled),
+            (None, stored) => stored,
+        }
+    }
+
+    fn find_week(&self, start: &dyn Week) -> Result<Vec<WorkRecord>> {
+        let _lock = self.lock_exclusive()?;
+        let replay = self.journal.replay()?;
+        let path = self.path_of_week(start);
+        let mut entries = JsonRepository::get_all_of_file(&path)?;
+        self.overlay(&path, &mut entries, &replay);
+        Ok(entries.into_values().collect())
+    }
+
+    /// Saves a record by appending the change to the journal.
+    fn persist(&mut self, entity: WorkRecord) -> Result<()> {
+        // other processes (i.e. commands run while the app is open) modify the same files
+        let _lock = self.lock_exclusive()?;
+        let mut replay = self.journal.replay()?;
+
+        let parts = split_into_days(entity);
+        let later = parts.last().map(later_parts).into_iter().flatten();
+        for (id, start) in later {
+            let exists = match replay.changes.get(&id) {
+                Some(Change::Saved(_)) => true,
+                Some(Change::Deleted) => false,
+                None => self.stored(&id, &start)?.is_some(),
+            };
+            if !exists {
+                break;
+            }
+            self.journal
+                .append(&mut replay, Event::Deleted { id, start })?;
+        }
+        for part in parts {
+            let stored = match replay.record(&part.id) {
+                Some(_) => None,
+                None => self.stored(&part.id, &part.start)?,
+            };
+            if let Some(event) = replay.event_for(stored.as_ref(), part) {
+                self.journal.append(&mut replay, event)?;
+            }
+        }
+
+        if replay.events >= SNAPSHOT_AFTER {
+            self.snapshot(&replay)?;
+        }
+        Ok(())
+    }
+
+    fn delete(&mut self, entity: &WorkRecord) -> Result<()> {
+        let _lock = self.lock_exclusive()?;
+        let mut replay = self.journal.replay()?;
+        for part in split_into_days(entity.clone()) {
+            let event = Event::Deleted {
+                id: part.id,
+                start: part.start,
+            };
+            self.journal.append(&mut replay, event)?;
+        }
+        Ok(())
+    }
+
+    /// Only reads the week files that may contain matching records according to the [Index].
+    fn find_range(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        project: Option<&str>,
+    ) -> Result<Vec<WorkRecord>> {
+        let _lock = self.lock_exclusive()?;
+        let replay = self.journal.replay()?;
+        let files = self.week_files()?;
+        let mut index = Index::load(&self.subfolder);
+        let mut records: Vec<WorkRecord> = replay.records().cloned().collect();
+        for path in &files {
+            let entries = match index.read_if_overlapping(
+                path,
+                from,
+                to,
+                project,
+                JsonRepository::get_all_of_file,
+            ) {
+                Ok(Some(entries)) => entries,
+                Ok(None) => continue,
+                Err(e) => {
+                    log!("⚠ skipped {}: {}", path.display(), e);
+                    continue;
+                }
+            };
+            records.extend(
+                entries
+                    .into_values()
+                    .filter(|record| !replay.changes.contains_key(&record.id)),
+            );
+        }
+        if let Err(e) = index.save(&files) {
+            log!(
+                "⚠ failed to save the index of {}: {}",
+                self.subfolder.display(),
+                e
+            );
+        }
+        records.retain(|record| {
+            record.overlaps(from, to) && project.is_none_or(|project| record.name == project)
+        });
+        Ok(records)
+    }
+
+    /// Records in week files other than the one of their start. Records changed in the journal
+    /// since are saved to the right file with the next snapshot.
+    fn misplaced(&self) -> Result<Vec<WorkRecord>> {
+        let _lock = self.lock_exclusive()?;
+        let replay = self.journal.replay()?;
+        let mut misplaced = vec![];
+        for path in self.week_files()? {
+            misplaced.extend(
+                JsonRepository::get_all_of_file(&path)?
+                    .into_values()
+                    .filter(|record| !replay.changes.contains_key(&record.id))
+                    .filter(|record| self.path_of_week(&record.start) != path),
+            );
+        }
+        Ok(misplaced)
+    }
+
+    fn upgrade(&mut self) -> Result<Option<PathBuf>> {
+        let _lock = self.lock_exclusive()?;
+        let mut outdated = vec![];
+        for path in self.week_files()? {
+            let (version, entries) = JsonRepository::read_week_file(&path)?;
+            if version < schema::VERSION {
+                outdated.push((path, entries));
+            }
+        }
+        if outdated.is_empty() {
+            return Ok(None);
+        }
+        let files: Vec<PathBuf> = outdated.iter().map(|(path, _)| path.clone()).collect();
+        let backup = self.backup(&files)?;
+        for (path, entries) in &outdated {
+            write_atomically(path, entries)?;
+        }
+        Ok(Some(backup))
+    }
+
+    fn all(&self) -> Result<Vec<WorkRecord>> {
+        let _lock = self.lock_exclusive()?;
+        let replay = self.journal.replay()?;
+        let mut records: HashMap<String, WorkRecord> = HashMap::new();
+        for path in self.week_files()? {
+            records.extend(JsonRepository::get_all_of_file(&path)?);
+        }
+        apply_changes(&mut records, replay);
+        Ok(records.into_values().collect())
+    }
+}
+
+/// Applies the changes in the journal to the records of all week files.
+fn apply_changes(records: &mut HashMap<String, WorkRecord>, replay: Replay) {
+    for (id, change) in replay.changes {
+        match change {
+            Change::Saved(record) => records.insert(id, record),
+            Change::Deleted => records.remove(&id),
+        };
+    }
+}
+
+/// all week files in `folder`, sorted from oldest to newest
+fn week_files_in(folder: &Path) -> io::Result<Vec<PathBuf>> {
+    let entries: Vec<DirEntry> = fs::read_dir(folder).and_then(Iterator::collect)?;
+    let mut files: Vec<PathBuf> = entries
+        .iter()
+        .map(DirEntry::path)
+        .filter(|path| is_week_file(path))
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+/// Renames a corrupt file to `<name>.corrupt-<timestamp>`, which is no longer read as a week file.
+fn quarantine(path: &Path) -> io::Result<PathBuf> {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".corrupt-{}", Local::now().format("%Y%m%dT%H%M%S")));
+    let backup = path.with_file_name(name);
+    fs::rename(path, &backup)?;
+    Ok(backup)
+}
+
+fn is_week_file(path: &Path) -> bool {
+    path.is_file()
+        && path
+            .extension()
+            .is_some_and(|extension| extension == "json")
+}
+
+/// Writes `entries` to a temporary file next to `path` and replaces `path` with it once
+/// everything is on disk, so a crash never leaves a truncated file behind.
+fn write_atomically(path: &Path, entries: &HashMap<String, WorkRecord>) -> Result<()> {
+    let temp_path = path.with_extension("json.tmp");
+    let file = File::create(&temp_path)?;
+    let mut writer = BufWriter::new(file);
+    schema::write(&mut writer, entries)?;
+    writer.flush()?;
+    writer.get_ref().sync_all()?;
+    drop(writer);
+    fs::rename(&temp_path, path)?;
+    // the rename itself is only durable once the directory is synced, which windows can't do
+    #[cfg(unix)]
+    if let Some(parent) = path.parent() {
+        File::open(parent)?.sync_all()?;
+    }
+    Ok(())
+}
+
+fn create_dir_if_not_exists(path: &Path) -> io::Result<()> {
+    if path.is_dir() {
+        Ok(())
+    } else {
+        fs::create_dir(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+
+    use crate::repository::model::{ProjectState, TimeKind};
+
+    use super::*;
+
+    fn record(id: &str, start: chrono::DateTime<Utc>) -> WorkRecord {
+        let mut record = WorkRecord::new("EKS".to_string(), BTreeSet::new(), start);
+        record.id = id.to_string();
+        record.close_at(start + chrono::Duration::hours(1));
+        record
+    }
+
+    fn repository(directory: &tempfile::TempDir) -> JsonRepository {
+        JsonRepository::new(directory.path()).unwrap()
+    }
+
+    #[test]
+    fn test_persist_and_find() {
+        let directory = tempfile::tempdir().unwrap();
+        let mut repository = repository(&directory);
+        let start = Utc.with_ymd_and_hms(2023, 7, 4, 8, 0, 0).unwrap();
+
+        repository.persist(record("a", start)).unwrap();
+        repository
+            .persist(record("b", start + chrono::Duration::hours(2)))
+            .unwrap();
+
+        let records = repository.find_week(&start).unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(repository.get_latest().unwrap().id, "b");
+        let leftovers: Vec<PathBuf> = fs::read_dir(&repository.subfolder)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            // hidden files are the lock and markers
+            .filter(|path| {
+                !is_week_file(path)
+                    && *path != directory.path().join("work_records/journal")
+                    && !path.file_name().unwrap().to_string_lossy().starts_with('.')
+            })
+            .collect();
+        assert!(leftovers.is_empty(), "unexpected files: {leftovers:?}");
+    }
+
+    #[test]
+    fn test_split_and_merge() {
+        let directory = tempfile::tempdir().unwrap();
+        let mut repository = repository(&directory);
+        let start = Utc.with_ymd_and_hms(2023, 7, 4, 8, 0, 0).unwrap();
+        let half_past = start + chrono::Duration::minutes(30);
+        let entity = record("a", start);
+        repository.persist(entity.clone()).unwrap();
+
+        let (first, second) = repository.split(&entity, half_past, Some("Swag")).unwrap();
+        assert!(repository.split(&first, half_past, None).is_err());
+        assert!(repository.merge(&first, &second).is_err());
+        let mut records = repository.find_week(&start).unwrap();
+        records.sort_by_key(|record| record.start);
+        assert_eq!(records, vec![first.clone(), second.clone()]);
+        assert_eq!(records[1].name, "Swag");
+
+        let (_, mut second) = repository.split(&entity, half_past, None).unwrap();
+        second.tags.insert("review".to_string());
+        let merged = repository.merge(&first, &second).unwrap();
+        assert_eq!(repository.find_week(&start).unwrap(), vec![merged.clone()]);
+        assert_eq!(merged.segments, entity.segments);
+        assert!(merged.tags.contains("review"));
+    }
+
+    #[test]
+    fn test_snapshot() {
+        let directory = tempfile::tempdir().unwrap();
+        let mut repository = repository(&directory);
+        let start = Utc.with_ymd_and_hms(2023, 7, 4, 8, 0, 0).unwrap();
+        let mut entity = record("a", start);
+        entity.end = None;
+        entity.state = ProjectState::Working;
+        entity.segments[0].end = None;
+        repository
+            .persist(record("b", start - chrono::Duration::hours(2)))
+            .unwrap();
+
+        for minute in 1..SNAPSHOT_AFTER as i64 {
+            entity.last_seen = Some(start + chrono::Duration::minutes(minute));
+            repository.persist(entity.clone()).unwrap();
+        }
+
+        let stored = JsonRepository::get_all_of_file(&repository.path_of_week(&start));
+        assert_eq!(stored.unwrap().len(), 2);
+        assert_eq!(repository.journal.replay().unwrap().events, 0);
+        let archived = fs::read_dir(directory.path().join("work_records/journal"))
+            .unwrap()
+            .count();
+        assert_eq!(archived, 1);
+        assert_eq!(repository.get_latest(), Some(entity.clone()));
+
+        // the next change only lives in the journal again
+        entity.close_at(start + chrono::Duration::hours(5));
+        repository.persist(entity.clone()).unwrap();
+        assert_eq!(repository.get_latest(), Some(entity));
+        assert_eq!(repository.find_week(&start).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_persist_after_delete_of_snapshot() {
+        let directory = tempfile::tempdir().unwrap();
+        let mut repository = repository(&directory);
+        let start = Utc.with_ymd_and_hms(2023, 7, 4, 8, 0, 0).unwrap();
+        let entity = record("a", start);
+        repository.persist(entity.clone()).unwrap();
+        repository.compact().unwrap();
+
+        repository.delete(&entity).unwrap();
+        assert!(repository.find_week(&start).unwrap().is_empty());
+        repository.persist(entity.clone()).unwrap();
+
+        assert_eq!(repository.find_week(&start).unwrap(), vec![entity.clone()]);
+        repository.compact().unwrap();
+        assert_eq!(repository.find_week(&start).unwrap(), vec![entity]);
+    }
+
+    #[test]
+    fn test_find_range() {
+        let directory = tempfile::tempdir().unwrap();
+        let mut repository = repository(&directory);
+        let start = Utc.with_ymd_and_hms(2023, 7, 4, 8, 0, 0).unwrap();
+        let mut other = record("b", start - chrono::Duration::days(7));
+        other.name = "Swag".to_string();
+        repository.persist(record("a", start)).unwrap();
+        repository.persist(other).unwrap();
+        repository.compact().unwrap();
+        // only lives in the journal
+        repository
+            .persist(record("c", start + chrono::Duration::days(1)))
+            .unwrap();
+
+        let ids = |records: Vec<WorkRecord>| {
+            let mut ids: Vec<String> = records.into_iter().map(|record| record.id).collect();
+            ids.sort();
+            ids
+        };
+        let week = repository.find_range(start, start + chrono::Duration::days(5), None);
+        assert_eq!(ids(week.unwrap()), vec!["a", "c"]);
+        assert!(directory.path().join("work_records/.index").is_file());
+        let month = repository.find_range(start - chrono::Duration::days(30), start, None);
+        assert_eq!(ids(month.unwrap()), vec!["b"]);
+        let swag = repository.find_range(
+            start - chrono::Duration::days(30),
+            start + chrono::Duration::days(30),
+            Some("Swag"),
+        );
+        assert_eq!(ids(swag.unwrap()), vec!["b"]);
+    }
+
+    #[test]
+    fn test_find_range_skips_unreadable_files() {
+        let directory = tempfile::tempdir().unwrap();
+        let mut repository = repository(&directory);
+        let start = Utc.with_ymd_and_hms(2023, 7, 4, 8, 0, 0).unwrap();
+        let next_week = start + chrono::Duration::days(7);
+        repository.persist(record("a", start)).unwrap();
+        repository.compact().unwrap();
+        fs::write(
+            repository.path_of_week(&next_week),
+            r#"{"version": 99, "records": {}}"#,
+        )
+        .unwrap();
+
+        let records = repository.find_range(start, next_week + chrono::Duration::days(1), None);
+        assert_eq!(records.unwrap(), vec![record("a", start)]);
+    }
+
+    #[test]
+    fn test_upgrade() {
+        let directory = tempfile::tempdir().unwrap();
+        let mut repository = repository(&directory);
+        let start = Utc.with_ymd_and_hms(2023, 7, 4, 8, 0, 0).unwrap();
+        let path = repository.path_of_week(&start);
+        let entries: HashMap<String, WorkRecord> = [("a".to_string(), record("a", start))].into();
+        fs::write(&path, serde_json::to_string(&entries).unwrap()).unwrap();
+
+        let backup = repository
+            .upgrade()
+            .unwrap()
+            .expect("should upgrade version 1");
+        assert!(backup.join("2023-27.json").is_file());
+        let file: serde_json::Value = serde_json::from_slice(&fs::read(&path).unwrap()).unwrap();
+        assert_eq!(schema::version_of(&file), schema::VERSION);
+        assert_eq!(
+            repository.find_week(&start).unwrap(),
+            vec![record("a", start)]
+        );
+        assert!(repository.upgrade().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_newer_file_is_not_quarantined() {
+        let directory = tempfile::tempdir().unwrap();
+        let repository = repository(&directory);
+        let start = Utc.with_ymd_and_hms(2023, 7, 4, 8, 0, 0).unwrap();
+        let path = repository.path_of_week(&start);
+        fs::write(&path, r#"{"version": 99, "records": {"a": 42}}"#).unwrap();
+
+        let result = repository.find_week(&start);
+        assert!(matches!(
+            result,
+            Err(RepositoryError::Unsupported { version: 99, .. })
+        ));
+        assert!(path.is_file());
+    }
+
+    #[test]
+    fn test_corrupt_file_is_quarantined() {
+        let directory = tempfile::tempdir().unwrap();
+        let mut repository = repository(&directory);
+        let previous_week = Utc.with_ymd_and_hms(2023, 6, 27, 8, 0, 0).unwrap();
+        let start = Utc.with_ymd_and_hms(2023, 7, 4, 8, 0, 0).unwrap();
+        repository.persist(record("a", previous_week)).unwrap();
+        fs::write(repository.path_of_week(&start), "{\"broken\":").unwrap();
+
+        assert_eq!(repository.get_latest().unwrap().id, "a");
+        let backups: Vec<PathBuf> = fs::read_dir(&repository.subfolder)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.to_string_lossy().contains(".corrupt-"))
+            .collect();
+        assert_eq!(backups.len(), 1);
+
+        assert!(repository.find_week(&start).unwrap().is_empty());
+        repository.persist(record("b", start)).unwrap();
+        assert_eq!(repository.get_latest().unwrap().id, "b");
+    }
+
+    #[test]
+    fn test_migrate_week_keys() {
+        let directory = tempfile::tempdir().unwrap();
+        let folder = directory.path().join("work_records");
+        fs::create_dir(&folder).unwrap();
+        // weeks used to start on sunday, so sunday was stored with the following monday, while
+        // it belongs to the ISO week of the saturday before
+        let saturday = Utc.with_ymd_and_hms(2023, 7, 1, 12, 0, 0).unwrap();
+        let sunday = Utc.with_ymd_and_hms(2023, 7, 2, 12, 0, 0).unwrap();
+        let monday = Utc.with_ymd_and_hms(2023, 7, 3, 12, 0, 0).unwrap();
+        let tuesday = Utc.with_ymd_and_hms(2023, 7, 4, 12, 0, 0).unwrap();
+        let old_files = [
+            ("2023-26.json", vec![record("saturday", saturday)]),
+            (
+                "2023-27.json",
+                vec![
+                    record("sunday", sunday),
+                    record("monday", monday),
+                    record("tuesday", tuesday),
+                ],
+            ),
+        ];
+        for (name, records) in old_files {
+            let entries: HashMap<String, WorkRecord> = records
+                .into_iter()
+                .map(|record| (record.id.clone(), record))
+                .collect();
+            write_atomically(&folder.join(name), &entries).unwrap();
+        }
+
+        let repository = repository(&directory);
+
+        let mut ids: Vec<String> = repository
+            .find_week(&monday)
+            .unwrap()
+            .into_iter()
+            .map(|record| record.id)
+            .collect();
+        ids.sort();
+        assert_eq!(ids, vec!["monday", "tuesday"]);
+        assert_eq!(repository.find_week(&sunday).unwrap().len(), 2);
+        assert!(folder.join(ISO_WEEKS_MARKER).exists());
+
+        // the migration only runs once
+        fs::write(folder.join("2023-30.json"), "{}").unwrap();
+        let repository = JsonRepository::new(directory.path()).unwrap();
+        assert_eq!(repository.week_files().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_migrate_corrupt_week_file() {
+        let directory = tempfile::tempdir().unwrap();
+        let folder = directory.path().join("work_records");
+        fs::create_dir(&folder).unwrap();
+        let monday = Utc.with_ymd_and_hms(2023, 7, 3, 12, 0, 0).unwrap();
+        let entries: HashMap<String, WorkRecord> =
+            [("monday".to_string(), record("monday", monday))].into();
+        write_atomically(&folder.join("2023-27.json"), &entries).unwrap();
+        fs::write(folder.join("2023-26.json"), "{\"broken\":").unwrap();
+
+        let repository = repository(&directory);
+
+        assert_eq!(repository.find_week(&monday).unwrap().len(), 1);
+        assert_eq!(repository.week_files().unwrap().len(), 1);
+        let quarantined = fs::read_dir(&folder)
+            .unwrap()
+            .filter(|entry| {
+                let name = entry.as_ref().unwrap().file_name();
+                name.to_string_lossy().starts_with("2023-26.json.corrupt-")
+            })
+            .count();
+        assert_eq!(quarantined, 1);
+    }
+
+    #[test]
+    fn test_read_all_leaves_source_alone() {
+        let directory = tempfile::tempdir().unwrap();
+        let folder = directory.path().join("work_records");
+        fs::create_dir(&folder).unwrap();
+        // named after the week starting on sunday, which a migration would move
+        let sunday = Utc.with_ymd_and_hms(2023, 7, 2, 12, 0, 0).unwrap();
+        let entries: HashMap<String, WorkRecord> =
+            [("sunday".to_string(), record("sunday", sunday))].into();
+        write_atomically(&folder.join("2023-27.json"), &entries).unwrap();
+
+        let records = JsonRepository::read_all(directory.path()).unwrap();
+
+        assert_eq!(records, vec![record("sunday", sunday)]);
+        let files: Vec<PathBuf> = fs::read_dir(&folder)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        assert_eq!(files, vec![folder.join("2023-27.json")]);
+
+        fs::write(folder.join("2023-28.json"), "{\"broken\":").unwrap();
+        assert!(JsonRepository::read_all(directory.path()).is_err());
+        assert!(folder.join("2023-28.json").is_file());
+    }
+
+    #[test]
+    fn test_persist_splits_at_week_boundary() {
+        let directory = tempfile::tempdir().unwrap();
+        let mut repository = repository(&directory);
+        let sunday = chrono::NaiveDate::from_ymd_opt(2023, 7, 2).unwrap();
+        let start = crate::time::parse_local("23:00", sunday).unwrap();
+        let end = crate::time::parse_local("2023-07-03 02:00", sunday).unwrap();
+        let mut entity = record("a", start);
+        entity.segments[0].end = None;
+        entity.close_at(end);
+
+        repository.persist(entity.clone()).unwrap();
+        // saving the same record again must not duplicate its parts
+        repository.persist(entity).unwrap();
+
+        let sunday_week = repository.find_week(&sunday).unwrap();
+        assert_eq!(sunday_week.len(), 1);
+        assert_eq!(sunday_week[0].id, "a");
+        assert_eq!(
+            sunday_week[0].calculate_duration(TimeKind::is_working_by_default),
+            chrono::Duration::hours(1)
+        );
+        let monday_week = repository.find_week(&end).unwrap();
+        assert_eq!(monday_week.len(), 1);
+        assert_eq!(
+            monday_week[0].calculate_duration(TimeKind::is_working_by_default),
+            chrono::Duration::hours(2)
+        );
+    }
+
+    #[test]
+    fn test_persist_and_delete_remove_later_parts() {
+        let directory = tempfile::tempdir().unwrap();
+        let mut repository = repository(&directory);
+        let tuesday = chrono::NaiveDate::from_ymd_opt(2023, 7, 4).unwrap();
+        let start = crate::time::parse_local("22:00", tuesday).unwrap();
+        let mut entity = record("a", start);
+        entity.segments[0].end = None;
+        entity.close_at(crate::time::parse_local("2023-07-05 02:00", tuesday).unwrap());
+        let all = |repository: &JsonRepository| {
+            repository
+                .find_range(start, start + chrono::Duration::days(2), None)
+                .unwrap()
+        };
+        repository.persist(entity.clone()).unwrap();
+        repository.compact().unwrap();
+
+        // changing the part before midnight keeps the part after it
+        let mut first = all(&repository)
+            .into_iter()
+            .find(|part| part.id == "a")
+            .unwrap();
+        first.description = Some("deployment".to_string());
+        repository.persist(first).unwrap();
+        assert_eq!(all(&repository).len(), 2);
+
+        let mut shortened = entity.clone();
+        shortened.segments[0].end = None;
+        shortened.close_at(start + chrono::Duration::hours(1));
+        repository.persist(shortened.clone()).unwrap();
+        assert_eq!(all(&repository), vec![shortened]);
+
+        repository.persist(entity.clone()).unwrap();
+        assert_eq!(all(&repository).len(), 2);
+        repository.delete(&entity).unwrap();
+        assert!(all(&repository).is_empty());
+        repository.compact().unwrap();
+        assert!(all(&repository).is_empty());
+    }
+
+    #[test]
+    fn test_corrupt_file_is_reported() {
+        let directory = tempfile::tempdir().unwrap();
+        let repository = repository(&directory);
+        let start = Utc.with_ymd_and_hms(2023, 7, 4, 8, 0, 0).unwrap();
+        fs::write(repository.path_of_week(&start), "[]").unwrap();
+
+        let err = repository.find_week(&start).expect_err("should be corrupt");
+        assert!(matches!(err, RepositoryError::Corrupt { .. }));
+    }
+}
@@ -0,0 +1,90 @@
+use std::fs::{self, File, OpenOptions};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process;
+
+use fs2::FileExt;
+
+use crate::log::log;
+
+const LOCK_FILE: &str = "track-work.lock";
+const PID_FILE: &str = "track-work.pid";
+
+/// Guarantees that only one instance of the app works on a data directory at a time. The lock is
+/// released when this is dropped or the process dies.
+#[derive(Debug)]
+pub struct InstanceLock {
+    // the operating system holds the lock as long as this file stays open
+    #[allow(dead_code)]
+    file: File,
+    pid_path: PathBuf,
+}
+
+impl InstanceLock {
+    pub fn acquire(directory: &Path) -> io::Result<InstanceLock> {
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(directory.join(LOCK_FILE))?;
+        // the pid is kept in a separate file, because windows doesn't allow reading locked files
+        let pid_path = directory.join(PID_FILE);
+        let holder = fs::read_to_string(&pid_path).ok();
+
+        if file.try_lock_exclusive().is_err() {
+            let holder = holder.map_or("unknown".to_string(), |pid| pid.trim().to_string());
+            return Err(io::Error::new(
+                io::ErrorKind::WouldBlock,
+                format!(
+                    "track-work is already running on {} (pid {})",
+                    directory.display(),
+                    holder
+                ),
+            ));
+        }
+        if let Some(pid) = holder {
+            log!(
+                "⚠ removed stale lock of a previous instance (pid {}) that did not shut down",
+                pid.trim()
+            );
+        }
+        fs::write(&pid_path, process::id().to_string())?;
+        Ok(InstanceLock { file, pid_path })
+    }
+}
+
+impl Drop for InstanceLock {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.pid_path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_second_instance_is_rejected() {
+        let directory = tempfile::tempdir().unwrap();
+
+        let lock = InstanceLock::acquire(directory.path()).expect("first lock should succeed");
+        let err = InstanceLock::acquire(directory.path()).expect_err("second lock should fail");
+        assert!(err.to_string().contains(&process::id().to_string()));
+
+        drop(lock);
+        InstanceLock::acquire(directory.path()).expect("lock should be free again");
+    }
+
+    #[test]
+    fn test_stale_lock_is_taken_over() {
+        let directory = tempfile::tempdir().unwrap();
+        fs::write(directory.path().join(PID_FILE), "4711").unwrap();
+
+        let _lock =
+            InstanceLock::acquire(directory.path()).expect("stale lock should be taken over");
+        assert_eq!(
+            fs::read_to_string(directory.path().join(PID_FILE)).unwrap(),
+            process::id().to_string()
+        );
+    }
+}
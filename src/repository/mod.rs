@@ -1,3 +1,12 @@
+pub mod check;
+pub mod error;
+pub mod index;
+pub mod journal;
+pub mod json;
+pub mod lock;
 pub mod model;
+pub mod schema;
+pub mod sqlite;
+pub mod trash;
 pub mod week;
 pub mod work_record;
@@ -1,25 +1,88 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
 use std::fmt::{Display, Formatter};
 use std::ops::Add;
+use uuid::Uuid;
 
-#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+use crate::time::next_local_midnight;
+
+/// Fields written by other (i.e. newer) versions of track-work, which are kept as they are.
+pub type Extra = BTreeMap<String, serde_json::Value>;
+
+/// What the time of a segment was spent with. Kinds other than these two are configured in
+/// `config.yml`, which also decides whether they count as working time and are billable.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Hash, Clone)]
+#[serde(from = "String", into = "String")]
 pub enum TimeKind {
     Productive,
     Pause,
+    /// e.g. a meeting or travel
+    Other(String),
+}
+
+impl TimeKind {
+    pub fn name(&self) -> &str {
+        match self {
+            TimeKind::Productive => "Productive",
+            TimeKind::Pause => "Pause",
+            TimeKind::Other(name) => name,
+        }
+    }
+
+    /// Whether the time counts as working time if the config doesn't say otherwise, which is
+    /// everything but pauses.
+    pub fn is_working_by_default(&self) -> bool {
+        *self != TimeKind::Pause
+    }
+}
+
+impl From<String> for TimeKind {
+    fn from(name: String) -> Self {
+        match name.as_str() {
+            "Productive" => TimeKind::Productive,
+            "Pause" => TimeKind::Pause,
+            _ => TimeKind::Other(name),
+        }
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+impl From<TimeKind> for String {
+    fn from(kind: TimeKind) -> Self {
+        kind.name().to_string()
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct TimeSegment {
     pub start: DateTime<Utc>,
     pub end: Option<DateTime<Utc>>,
     pub kind: TimeKind,
+    /// why the work was paused, e.g. `lunch`, only set on pauses
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    #[serde(flatten)]
+    pub extra: Extra,
 }
 
 impl TimeSegment {
-    pub fn finish(&mut self) {
+    /// The part of this segment between `from` and `to`, segments that are still open last
+    /// until `now`.
+    pub fn duration_between(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        now: DateTime<Utc>,
+    ) -> chrono::Duration {
+        let start = self.start.max(from);
+        let end = self.end.unwrap_or(now).min(to);
+        end.signed_duration_since(start)
+            .max(chrono::Duration::zero())
+    }
+
+    pub fn finish_at(&mut self, end: DateTime<Utc>) {
         if self.end.is_none() {
-            self.end = Some(Utc::now());
+            self.end = Some(end);
         }
     }
 }
@@ -31,7 +94,71 @@ pub enum ProjectState {
     Done,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+impl ProjectState {
+    /// Whether a record in this state may change to `next`. Work and pauses alternate until the
+    /// record is done, which is final.
+    pub fn can_become(&self, next: &ProjectState) -> bool {
+        matches!(
+            (self, next),
+            (ProjectState::Working, ProjectState::Paused)
+                | (ProjectState::Paused, ProjectState::Working)
+                | (
+                    ProjectState::Working | ProjectState::Paused,
+                    ProjectState::Done
+                )
+        )
+    }
+
+    fn describe(&self) -> &'static str {
+        match self {
+            ProjectState::Working => "working",
+            ProjectState::Paused => "paused",
+            ProjectState::Done => "done",
+        }
+    }
+}
+
+/// A record was asked to change into a state it can't reach from its current one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InvalidTransition {
+    pub from: ProjectState,
+    pub to: ProjectState,
+}
+
+impl Display for InvalidTransition {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.from == self.to || self.from == ProjectState::Done {
+            write!(f, "is already {}", self.from.describe())
+        } else {
+            write!(
+                f,
+                "can't be {} while {}",
+                self.to.describe(),
+                self.from.describe()
+            )
+        }
+    }
+}
+
+/// Two records can't be merged into one, see [WorkRecord::merge].
+#[derive(Debug, Clone, PartialEq)]
+pub enum MergeError {
+    OtherProject,
+    StillRunning,
+    NotAfter,
+}
+
+impl Display for MergeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MergeError::OtherProject => write!(f, "can only be merged with the same project"),
+            MergeError::StillRunning => write!(f, "can't be merged while it is running"),
+            MergeError::NotAfter => write!(f, "can only be merged with a record after it"),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct WorkRecord {
     pub id: String,
     pub name: String,
@@ -39,34 +166,335 @@ pub struct WorkRecord {
     pub end: Option<DateTime<Utc>>,
     pub state: ProjectState,
     pub segments: Vec<TimeSegment>,
+    /// last time the running app confirmed that this record is still active
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_seen: Option<DateTime<Utc>>,
+    /// what was done, e.g. for the booking in the billing system
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// what kind of work this was across projects, e.g. `meeting` or `review`
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub tags: BTreeSet<String>,
+    #[serde(flatten)]
+    pub extra: Extra,
 }
 
 impl WorkRecord {
-    pub fn calculate_duration(&self) -> chrono::Duration {
+    /// A record of `name` that is worked on from `start` on.
+    pub fn new(name: String, tags: BTreeSet<String>, start: DateTime<Utc>) -> WorkRecord {
+        WorkRecord {
+            id: Uuid::new_v4().to_string(),
+            name,
+            start,
+            end: None,
+            state: ProjectState::Working,
+            segments: vec![TimeSegment {
+                start,
+                end: None,
+                kind: TimeKind::Productive,
+                reason: None,
+                extra: Extra::new(),
+            }],
+            last_seen: None,
+            description: None,
+            tags,
+            extra: Extra::new(),
+        }
+    }
+
+    /// Shows this record with the time spent on the kinds that are `counted`, which are the
+    /// working kinds of the config wherever it is shown to the user.
+    pub fn display<F: Fn(&TimeKind) -> bool>(&self, counted: F) -> RecordDisplay<'_, F> {
+        RecordDisplay {
+            record: self,
+            counted,
+        }
+    }
+
+    /// The time of this record spent with the kinds that are `counted`.
+    pub fn calculate_duration(&self, counted: impl Fn(&TimeKind) -> bool) -> chrono::Duration {
+        self.calculate_duration_between(DateTime::<Utc>::MIN_UTC, DateTime::<Utc>::MAX_UTC, counted)
+    }
+
+    /// The time of this record that falls between `from` and `to` and is spent with the kinds
+    /// that are `counted`, e.g. working or billable time.
+    pub fn calculate_duration_between(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        counted: impl Fn(&TimeKind) -> bool,
+    ) -> chrono::Duration {
+        let now = Utc::now();
         self.segments
             .iter()
-            .filter(|segment| segment.kind == TimeKind::Productive)
-            .map(|segment| {
-                if let Some(end) = segment.end {
-                    end.signed_duration_since(segment.start)
-                } else {
-                    Utc::now().signed_duration_since(segment.start)
-                }
+            .filter(|segment| counted(&segment.kind))
+            .map(|segment| segment.duration_between(from, to, now))
+            .fold(chrono::Duration::zero(), Add::add)
+    }
+
+    /// The id of the part of the record `id` that starts where it was split at `at`.
+    pub fn id_of_part(id: &str, at: DateTime<Utc>) -> String {
+        let name = format!("{}@{}", id, at.to_rfc3339());
+        Uuid::new_v5(&Uuid::NAMESPACE_OID, name.as_bytes()).to_string()
+    }
+
+    /// Splits this record in two at `at`. The first part keeps the id and is done, the second one
+    /// continues in the state of this record. The id of the second part is derived from the id
+    /// of this record and `at`, so splitting the same record twice yields the same records.
+    pub fn split_at(&self, at: DateTime<Utc>) -> Option<(WorkRecord, WorkRecord)> {
+        if at <= self.start || self.end.is_some_and(|end| at >= end) {
+            return None;
+        }
+        let mut first = self.clone();
+        first.close_at(at);
+        first.last_seen = None;
+
+        let mut second = self.clone();
+        second.id = WorkRecord::id_of_part(&self.id, at);
+        second.start = at;
+        second.segments = self
+            .segments
+            .iter()
+            .filter(|segment| segment.end.is_none_or(|end| end > at))
+            .cloned()
+            .map(|mut segment| {
+                segment.start = segment.start.max(at);
+                segment
             })
-            .reduce(Add::add)
-            .expect("There should always be at least one segment to calculate a Duration")
+            .collect();
+        Some((first, second))
+    }
+
+    /// Splits this record at every local midnight between its start and its end (or `until` for
+    /// records that are still open), so that each part lies within a single day and week.
+    pub fn split_at_midnight(&self, until: DateTime<Utc>) -> Vec<WorkRecord> {
+        let end = self.end.unwrap_or(until);
+        let mut parts = vec![];
+        let mut rest = self.clone();
+        loop {
+            let midnight = next_local_midnight(&rest.start);
+            if midnight >= end {
+                break;
+            }
+            match rest.split_at(midnight) {
+                Some((first, second)) => {
+                    parts.push(first);
+                    rest = second;
+                }
+                None => break,
+            }
+        }
+        parts.push(rest);
+        parts
+    }
+
+    /// Joins `next`, a later record of the same project, onto this one. The time between the two
+    /// becomes a pause, segments of the same kind that meet are joined. The merged record keeps
+    /// the id of this one and continues in the state of `next`.
+    pub fn merge(&self, next: &WorkRecord) -> Result<WorkRecord, MergeError> {
+        if next.name != self.name {
+            return Err(MergeError::OtherProject);
+        }
+        let Some(end) = self.end else {
+            return Err(MergeError::StillRunning);
+        };
+        if next.start < end {
+            return Err(MergeError::NotAfter);
+        }
+        let mut merged = self.clone();
+        let gap = TimeSegment {
+            start: end,
+            end: Some(next.start),
+            kind: TimeKind::Pause,
+            reason: None,
+            extra: Extra::new(),
+        };
+        for segment in Some(gap)
+            .filter(|_| next.start > end)
+            .into_iter()
+            .chain(next.segments.iter().cloned())
+        {
+            match merged.segments.last_mut() {
+                Some(last)
+                    if last.kind == segment.kind
+                        && last.reason == segment.reason
+                        && last.end == Some(segment.start) =>
+                {
+                    last.end = segment.end
+                }
+                _ => merged.segments.push(segment),
+            }
+        }
+        merged.end = next.end;
+        merged.state = next.state.clone();
+        merged.last_seen = next.last_seen;
+        merged.description = match (&self.description, &next.description) {
+            (Some(first), Some(second)) if first != second => Some(format!("{first}; {second}")),
+            (first, second) => first.clone().or(second.clone()),
+        };
+        merged.tags.extend(next.tags.iter().cloned());
+        Ok(merged)
+    }
+
+    /// Whether any part of this record lies between `from` and `to`.
+    pub fn overlaps(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> bool {
+        self.start < to && self.end.is_none_or(|end| end > from)
+    }
+
+    /// Changes the state of this record at `at`. The open segment ends exactly where the next one
+    /// starts, and a record that is done ends where its last segment does. `at` is moved to the
+    /// start of the open segment if it lies before it, so segments never overlap.
+    pub fn transition(
+        &mut self,
+        next: ProjectState,
+        at: DateTime<Utc>,
+    ) -> Result<(), InvalidTransition> {
+        if !self.state.can_become(&next) {
+            return Err(InvalidTransition {
+                from: self.state.clone(),
+                to: next,
+            });
+        }
+        match next {
+            ProjectState::Working => self.begin_segment(TimeKind::Productive, at),
+            ProjectState::Paused => self.begin_segment(TimeKind::Pause, at),
+            ProjectState::Done => self.close_at(self.not_before_open_segment(at)),
+        }
+        Ok(())
+    }
+
+    /// Pauses this record at `at`, optionally giving a `reason`.
+    pub fn pause_at(
+        &mut self,
+        at: DateTime<Utc>,
+        reason: Option<String>,
+    ) -> Result<(), InvalidTransition> {
+        self.transition(ProjectState::Paused, at)?;
+        if let Some(segment) = self.segments.last_mut() {
+            segment.reason = reason;
+        }
+        Ok(())
+    }
+
+    /// Continues this record with time of `kind` from `at` on, e.g. a meeting. A pause pauses the
+    /// record, every other kind means it is being worked on.
+    pub fn switch_kind(
+        &mut self,
+        kind: TimeKind,
+        at: DateTime<Utc>,
+    ) -> Result<(), InvalidTransition> {
+        if self.state == ProjectState::Done {
+            return Err(InvalidTransition {
+                from: ProjectState::Done,
+                to: ProjectState::Working,
+            });
+        }
+        self.begin_segment(kind, at);
+        Ok(())
+    }
+
+    fn not_before_open_segment(&self, at: DateTime<Utc>) -> DateTime<Utc> {
+        self.segments
+            .last()
+            .map_or(at, |segment| at.max(segment.start))
+    }
+
+    fn begin_segment(&mut self, kind: TimeKind, at: DateTime<Utc>) {
+        let at = self.not_before_open_segment(at);
+        self.state = match kind {
+            TimeKind::Pause => ProjectState::Paused,
+            _ => ProjectState::Working,
+        };
+        match self.segments.last_mut() {
+            // a segment without any length is simply turned into the next one
+            Some(segment) if segment.start == at => {
+                segment.kind = kind;
+                segment.reason = None;
+            }
+            last_segment => {
+                if let Some(segment) = last_segment {
+                    segment.finish_at(at);
+                }
+                self.segments.push(TimeSegment {
+                    start: at,
+                    end: None,
+                    kind,
+                    reason: None,
+                    extra: Extra::new(),
+                });
+            }
+        }
+        // ... and joins the previous one if that is of the same kind
+        if let [.., previous, last] = &mut self.segments[..] {
+            if previous.kind == last.kind
+                && previous.reason == last.reason
+                && previous.end == Some(last.start)
+            {
+                previous.end = last.end;
+                self.segments.pop();
+            }
+        }
+    }
+
+    /// Closes this record at `end`, dropping or shortening all segments that reach beyond it.
+    pub fn close_at(&mut self, end: DateTime<Utc>) {
+        self.segments.retain(|segment| segment.start < end);
+        for segment in self.segments.iter_mut() {
+            if segment.end.is_none_or(|segment_end| segment_end > end) {
+                segment.end = Some(end);
+            }
+        }
+        self.state = ProjectState::Done;
+        self.end = Some(end);
+    }
+
+    /// The last point in time this record is known to have been active, which is its heartbeat
+    /// or, if it never had one, the latest boundary of its segments.
+    pub fn last_activity(&self) -> DateTime<Utc> {
+        let segments = self
+            .segments
+            .iter()
+            .map(|segment| segment.end.unwrap_or(segment.start));
+        segments
+            .chain(self.last_seen)
+            .chain(Some(self.start))
+            .max()
+            .expect("there is always at least the start")
+    }
+
+    /// Retroactively turns the time between `from` and `to` into a pause, e.g. because the system
+    /// was suspended. Only affects records that are currently being worked on, paused records are
+    /// already covered by their open pause segment.
+    pub fn insert_pause(&mut self, from: DateTime<Utc>, to: DateTime<Utc>) {
+        if self.state != ProjectState::Working || from >= to {
+            return;
+        }
+        // the work continues with whatever it was before
+        let kind = self
+            .segments
+            .last()
+            .map_or(TimeKind::Productive, |segment| segment.kind.clone());
+        self.begin_segment(TimeKind::Pause, from);
+        self.begin_segment(kind, to);
     }
 }
 
-impl Display for WorkRecord {
+/// Shows a record with the time spent on the kinds that are `counted`, see [WorkRecord::display].
+pub struct RecordDisplay<'a, F> {
+    record: &'a WorkRecord,
+    counted: F,
+}
+
+impl<F: Fn(&TimeKind) -> bool> Display for RecordDisplay<'_, F> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        let icon = match self.state {
+        let record = self.record;
+        let icon = match record.state {
             ProjectState::Working => "♪",
             ProjectState::Paused => "𝄽",
             ProjectState::Done => "✓",
         };
 
-        let end_description = if let Some(end) = self.end {
+        let end_description = if let Some(end) = record.end {
             format!(
                 " - {}",
                 end.with_timezone(chrono::Local::now().offset())
@@ -76,21 +504,387 @@ impl Display for WorkRecord {
             "".to_string()
         };
 
-        let duration = self.calculate_duration();
+        let duration = record.calculate_duration(&self.counted);
+
+        let description = match &record.description {
+            Some(description) => format!(" · {description}"),
+            None => "".to_string(),
+        };
 
         let result = format!(
-            "{} {}: {}{} (time spent: {:02}:{:02}:{:02})",
+            "{} {}: {}{} (time spent: {:02}:{:02}:{:02}){}",
             icon,
-            self.name,
-            self.start
+            record.name,
+            record
+                .start
                 .with_timezone(chrono::Local::now().offset())
                 .format("%Y-%m-%d %H:%M"),
             end_description,
             duration.num_hours(),
             duration.num_minutes() % 60,
-            duration.num_seconds() % 60
+            duration.num_seconds() % 60,
+            description
         );
 
         f.write_str(&result)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn working_record(start: DateTime<Utc>) -> WorkRecord {
+        let mut record = WorkRecord::new("EKS".to_string(), BTreeSet::new(), start);
+        record.id = "id".to_string();
+        record
+    }
+
+    #[test]
+    fn test_insert_pause() {
+        let start = Utc.with_ymd_and_hms(2023, 7, 3, 17, 0, 0).unwrap();
+        let suspended = Utc.with_ymd_and_hms(2023, 7, 3, 18, 0, 0).unwrap();
+        let resumed = Utc.with_ymd_and_hms(2023, 7, 4, 8, 0, 0).unwrap();
+        let mut record = working_record(start);
+
+        record.insert_pause(suspended, resumed);
+
+        assert_eq!(record.segments.len(), 3);
+        assert_eq!(record.segments[0].end, Some(suspended));
+        assert_eq!(record.segments[1].kind, TimeKind::Pause);
+        assert_eq!(record.segments[1].start, suspended);
+        assert_eq!(record.segments[1].end, Some(resumed));
+        assert_eq!(record.segments[2].kind, TimeKind::Productive);
+        assert_eq!(record.segments[2].start, resumed);
+        assert_eq!(record.segments[2].end, None);
+    }
+
+    #[test]
+    fn test_insert_pause_at_segment_boundary() {
+        let start = Utc.with_ymd_and_hms(2023, 7, 3, 8, 0, 0).unwrap();
+        let meeting = TimeKind::Other("Meeting".to_string());
+        let mut record = working_record(start);
+        record
+            .switch_kind(meeting.clone(), start + chrono::Duration::hours(1))
+            .unwrap();
+
+        record.insert_pause(
+            start + chrono::Duration::hours(1),
+            start + chrono::Duration::hours(2),
+        );
+
+        let kinds: Vec<&TimeKind> = record.segments.iter().map(|s| &s.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![&TimeKind::Productive, &TimeKind::Pause, &meeting]
+        );
+        assert_eq!(
+            record.segments[1].end,
+            Some(start + chrono::Duration::hours(2))
+        );
+        assert_eq!(record.state, ProjectState::Working);
+    }
+
+    #[test]
+    fn test_insert_pause_while_paused() {
+        let start = Utc.with_ymd_and_hms(2023, 7, 3, 17, 0, 0).unwrap();
+        let mut record = working_record(start);
+        record.state = ProjectState::Paused;
+
+        record.insert_pause(start, start + chrono::Duration::hours(1));
+
+        assert_eq!(record.segments.len(), 1);
+    }
+
+    #[test]
+    fn test_close_at() {
+        let start = Utc.with_ymd_and_hms(2023, 7, 3, 8, 0, 0).unwrap();
+        let mut record = working_record(start);
+        record.insert_pause(
+            start + chrono::Duration::hours(2),
+            start + chrono::Duration::hours(3),
+        );
+        record.last_seen = Some(start + chrono::Duration::hours(4));
+        assert_eq!(record.last_activity(), start + chrono::Duration::hours(4));
+
+        record.close_at(start + chrono::Duration::minutes(150));
+
+        assert_eq!(record.state, ProjectState::Done);
+        assert_eq!(record.end, Some(start + chrono::Duration::minutes(150)));
+        assert_eq!(record.segments.len(), 2);
+        assert_eq!(
+            record.segments[1].end,
+            Some(start + chrono::Duration::minutes(150))
+        );
+        assert_eq!(
+            record.calculate_duration(TimeKind::is_working_by_default),
+            chrono::Duration::hours(2)
+        );
+    }
+
+    #[test]
+    fn test_calculate_duration_between() {
+        let start = Utc.with_ymd_and_hms(2023, 7, 2, 22, 0, 0).unwrap();
+        let mut record = working_record(start);
+        record.insert_pause(
+            start + chrono::Duration::hours(1),
+            start + chrono::Duration::hours(2),
+        );
+        record.close_at(start + chrono::Duration::hours(4));
+
+        assert_eq!(
+            record.calculate_duration(TimeKind::is_working_by_default),
+            chrono::Duration::hours(3)
+        );
+        assert_eq!(
+            record.calculate_duration_between(
+                start + chrono::Duration::minutes(30),
+                start + chrono::Duration::hours(3),
+                TimeKind::is_working_by_default
+            ),
+            chrono::Duration::minutes(90)
+        );
+        assert_eq!(
+            record.calculate_duration_between(
+                start - chrono::Duration::hours(2),
+                start,
+                TimeKind::is_working_by_default
+            ),
+            chrono::Duration::zero()
+        );
+    }
+
+    #[test]
+    fn test_transition() {
+        let start = Utc.with_ymd_and_hms(2023, 7, 3, 8, 0, 0).unwrap();
+        let hour = |hours: i64| start + chrono::Duration::hours(hours);
+        let mut record = working_record(start);
+
+        assert_eq!(
+            record.transition(ProjectState::Working, hour(1)),
+            Err(InvalidTransition {
+                from: ProjectState::Working,
+                to: ProjectState::Working
+            })
+        );
+        record.transition(ProjectState::Paused, hour(1)).unwrap();
+        assert!(record.transition(ProjectState::Paused, hour(2)).is_err());
+        record.transition(ProjectState::Working, hour(2)).unwrap();
+        // the pause is turned back into work instead of leaving an empty segment behind
+        record.transition(ProjectState::Paused, hour(3)).unwrap();
+        record.transition(ProjectState::Working, hour(3)).unwrap();
+        record.transition(ProjectState::Done, hour(4)).unwrap();
+        assert!(record.transition(ProjectState::Working, hour(5)).is_err());
+
+        assert_eq!(record.state, ProjectState::Done);
+        assert_eq!(record.end, Some(hour(4)));
+        let kinds: Vec<_> = record.segments.iter().map(|s| s.kind.clone()).collect();
+        assert_eq!(
+            kinds,
+            vec![TimeKind::Productive, TimeKind::Pause, TimeKind::Productive]
+        );
+        for (segment, next) in record.segments.iter().zip(record.segments.iter().skip(1)) {
+            assert_eq!(segment.end, Some(next.start));
+        }
+        assert_eq!(
+            record.calculate_duration(TimeKind::is_working_by_default),
+            chrono::Duration::hours(3)
+        );
+    }
+
+    #[test]
+    fn test_switch_kind() {
+        let start = Utc.with_ymd_and_hms(2023, 7, 3, 8, 0, 0).unwrap();
+        let hour = |hours: i64| start + chrono::Duration::hours(hours);
+        let meeting = TimeKind::from("Meeting".to_string());
+        let mut record = working_record(start);
+
+        record.switch_kind(meeting.clone(), hour(1)).unwrap();
+        record.switch_kind(meeting.clone(), hour(2)).unwrap();
+        record.insert_pause(hour(3), hour(4));
+        record.transition(ProjectState::Done, hour(5)).unwrap();
+        assert!(record.switch_kind(meeting.clone(), hour(6)).is_err());
+
+        let kinds: Vec<_> = record.segments.iter().map(|s| s.kind.clone()).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                TimeKind::Productive,
+                meeting.clone(),
+                TimeKind::Pause,
+                meeting.clone()
+            ]
+        );
+        assert_eq!(
+            record.calculate_duration(|kind| *kind == meeting),
+            chrono::Duration::hours(3)
+        );
+        assert_eq!(
+            record.calculate_duration(TimeKind::is_working_by_default),
+            chrono::Duration::hours(4)
+        );
+    }
+
+    #[test]
+    fn test_display_counts_the_given_kinds() {
+        let start = Utc.with_ymd_and_hms(2023, 7, 3, 8, 0, 0).unwrap();
+        let lunch = TimeKind::from("Lunch".to_string());
+        let mut record = working_record(start);
+        record
+            .switch_kind(lunch.clone(), start + chrono::Duration::hours(1))
+            .unwrap();
+        record
+            .transition(ProjectState::Done, start + chrono::Duration::hours(2))
+            .unwrap();
+
+        let all = record.display(TimeKind::is_working_by_default).to_string();
+        assert!(all.contains("(time spent: 02:00:00)"), "{all}");
+        let working = record.display(|kind| *kind != lunch).to_string();
+        assert!(working.contains("(time spent: 01:00:00)"), "{working}");
+    }
+
+    #[test]
+    fn test_merge() {
+        let start = Utc.with_ymd_and_hms(2023, 7, 3, 8, 0, 0).unwrap();
+        let hour = |hours: i64| start + chrono::Duration::hours(hours);
+        let mut first = working_record(start);
+        first.pause_at(hour(1), None).unwrap();
+        first.transition(ProjectState::Done, hour(2)).unwrap();
+        first.description = Some("review".to_string());
+        let mut second = working_record(hour(3));
+        second.id = "second".to_string();
+        second.pause_at(hour(4), Some("lunch".to_string())).unwrap();
+        second.tags.insert("meeting".to_string());
+
+        assert_eq!(second.merge(&first), Err(MergeError::StillRunning));
+        let mut other = first.clone();
+        other.name = "Swag".to_string();
+        assert_eq!(other.merge(&second), Err(MergeError::OtherProject));
+        let merged = first.merge(&second).unwrap();
+
+        let segments: Vec<_> = merged
+            .segments
+            .iter()
+            .map(|s| (s.start, s.end, s.kind.clone(), s.reason.as_deref()))
+            .collect();
+        assert_eq!(
+            segments,
+            vec![
+                (hour(0), Some(hour(1)), TimeKind::Productive, None),
+                (hour(1), Some(hour(3)), TimeKind::Pause, None),
+                (hour(3), Some(hour(4)), TimeKind::Productive, None),
+                (hour(4), None, TimeKind::Pause, Some("lunch")),
+            ]
+        );
+        assert_eq!(merged.id, "id");
+        assert_eq!(merged.state, ProjectState::Paused);
+        assert_eq!(merged.end, None);
+        assert_eq!(merged.description.as_deref(), Some("review"));
+        assert!(merged.tags.contains("meeting"));
+    }
+
+    #[test]
+    fn test_pause_at_with_reason() {
+        let start = Utc.with_ymd_and_hms(2023, 7, 3, 8, 0, 0).unwrap();
+        let hour = |hours: i64| start + chrono::Duration::hours(hours);
+        let mut record = working_record(start);
+
+        record.pause_at(hour(1), Some("lunch".to_string())).unwrap();
+        record.transition(ProjectState::Working, hour(2)).unwrap();
+        record
+            .pause_at(hour(3), Some("coffee".to_string()))
+            .unwrap();
+        record
+            .pause_at(hour(3), Some("interruption".to_string()))
+            .unwrap_err();
+
+        let reasons: Vec<_> = record
+            .segments
+            .iter()
+            .map(|s| s.reason.as_deref())
+            .collect();
+        assert_eq!(reasons, vec![None, Some("lunch"), None, Some("coffee")]);
+        assert_eq!(record.state, ProjectState::Paused);
+    }
+
+    #[test]
+    fn test_kinds_are_stored_by_name() {
+        let kinds = vec![
+            TimeKind::Productive,
+            TimeKind::Pause,
+            TimeKind::Other("Meeting".to_string()),
+        ];
+        let json = serde_json::to_string(&kinds).unwrap();
+        assert_eq!(json, r#"["Productive","Pause","Meeting"]"#);
+        assert_eq!(serde_json::from_str::<Vec<TimeKind>>(&json).unwrap(), kinds);
+    }
+
+    #[test]
+    fn test_transition_before_open_segment() {
+        let start = Utc.with_ymd_and_hms(2023, 7, 3, 8, 0, 0).unwrap();
+        let mut record = working_record(start);
+
+        record
+            .transition(ProjectState::Paused, start - chrono::Duration::minutes(1))
+            .unwrap();
+
+        assert_eq!(record.segments.len(), 1);
+        assert_eq!(record.segments[0].kind, TimeKind::Pause);
+        assert_eq!(record.segments[0].start, start);
+    }
+
+    #[test]
+    fn test_split_at() {
+        let start = Utc.with_ymd_and_hms(2023, 7, 2, 22, 0, 0).unwrap();
+        let mut record = working_record(start);
+        record.insert_pause(
+            start + chrono::Duration::hours(1),
+            start + chrono::Duration::hours(2),
+        );
+        let at = start + chrono::Duration::minutes(90);
+
+        let (first, second) = record.split_at(at).expect("should split");
+
+        assert_eq!(first.id, record.id);
+        assert_eq!(first.state, ProjectState::Done);
+        assert_eq!(first.end, Some(at));
+        assert_eq!(first.segments.len(), 2);
+        assert_ne!(second.id, record.id);
+        assert_eq!(second.id, record.split_at(at).unwrap().1.id);
+        assert_eq!(second.state, ProjectState::Working);
+        assert_eq!(second.start, at);
+        assert_eq!(second.segments.len(), 2);
+        assert_eq!(second.segments[0].kind, TimeKind::Pause);
+        assert_eq!(second.segments[0].start, at);
+        assert!(record.split_at(start).is_none());
+    }
+
+    #[test]
+    fn test_split_at_midnight() {
+        let day = chrono::NaiveDate::from_ymd_opt(2023, 7, 2).unwrap();
+        let start = crate::time::parse_local("23:00", day).unwrap();
+        let end = crate::time::parse_local("2023-07-04 02:00", day).unwrap();
+        let mut record = working_record(start);
+        record.close_at(end);
+
+        let parts = record.split_at_midnight(Utc::now());
+
+        assert_eq!(parts.len(), 3);
+        assert_eq!(
+            parts[0].calculate_duration(TimeKind::is_working_by_default),
+            chrono::Duration::hours(1)
+        );
+        assert_eq!(
+            parts[1].calculate_duration(TimeKind::is_working_by_default),
+            chrono::Duration::hours(24)
+        );
+        assert_eq!(
+            parts[2].calculate_duration(TimeKind::is_working_by_default),
+            chrono::Duration::hours(2)
+        );
+        assert_eq!(parts[2].end, Some(end));
+        assert!(parts.iter().all(|part| part.state == ProjectState::Done));
+    }
+}
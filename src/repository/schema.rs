@@ -0,0 +1,131 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::repository::model::WorkRecord;
+
+/// The version of the week files written by this version of track-work.
+pub const VERSION: u32 = 3;
+
+/// Turns a week file of version `n + 1` into one of version `n + 2`.
+const MIGRATIONS: [fn(Value) -> Value; VERSION as usize - 1] = [
+    // 1 → 2: the bare map of records is wrapped to make room for the version
+    |records| json!({ "version": 2, "records": records }),
+    // 2 → 3: segments may be of configured kinds, which older versions don't know
+    |mut file| {
+        file["version"] = json!(3);
+        file
+    },
+];
+
+/// The version of journal entries and trashed records that don't name one, they were written
+/// before versions were added there. Records are read the same way in every version so far,
+/// only the layout of week files changed, so just newer versions are refused.
+pub fn unversioned() -> u32 {
+    1
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct WeekFile<T> {
+    version: u32,
+    records: T,
+}
+
+#[derive(Debug)]
+pub enum SchemaError {
+    /// the file was written by a newer version of track-work
+    Unsupported(u32),
+    Invalid(serde_json::Error),
+}
+
+/// The version of a week file, files without one are of version 1.
+pub fn version_of(file: &Value) -> u32 {
+    file.get("version")
+        .and_then(Value::as_u64)
+        .filter(|_| file.get("records").is_some())
+        .map_or(1, |version| version as u32)
+}
+
+/// Reads the records of a week file of any version up to [VERSION], together with the version
+/// the file was in.
+pub fn read(mut file: Value) -> Result<(u32, HashMap<String, WorkRecord>), SchemaError> {
+    let version = version_of(&file);
+    if version > VERSION || version == 0 {
+        return Err(SchemaError::Unsupported(version));
+    }
+    for migration in &MIGRATIONS[version as usize - 1..] {
+        file = migration(file);
+    }
+    serde_json::from_value::<WeekFile<HashMap<String, WorkRecord>>>(file)
+        .map(|file| (version, file.records))
+        .map_err(SchemaError::Invalid)
+}
+
+/// Writes `records` as a week file of the current [VERSION].
+pub fn write<W: std::io::Write>(
+    writer: W,
+    records: &HashMap<String, WorkRecord>,
+) -> serde_json::Result<()> {
+    serde_json::to_writer(
+        writer,
+        &WeekFile {
+            version: VERSION,
+            records,
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RECORD: &str = r#"{
+        "id": "a",
+        "name": "EKS",
+        "start": "2023-07-04T08:00:00Z",
+        "end": "2023-07-04T09:00:00Z",
+        "state": "Done",
+        "segments": [{
+            "start": "2023-07-04T08:00:00Z",
+            "end": "2023-07-04T09:00:00Z",
+            "kind": "Productive",
+            "note": "from the future"
+        }],
+        "priority": "high"
+    }"#;
+
+    #[test]
+    fn test_read_version_1() {
+        let file: Value = serde_json::from_str(&format!(r#"{{"a": {RECORD}}}"#)).unwrap();
+        let (version, records) = read(file).unwrap();
+        assert_eq!(version, 1);
+        assert_eq!(records["a"].name, "EKS");
+    }
+
+    #[test]
+    fn test_unknown_fields_are_kept() {
+        let file: Value = serde_json::from_str(&format!(
+            r#"{{"version": 2, "records": {{"a": {RECORD}}}}}"#
+        ))
+        .unwrap();
+        let (version, records) = read(file).unwrap();
+        assert_eq!(version, 2);
+        assert_eq!(records["a"].extra["priority"], json!("high"));
+
+        let mut written = vec![];
+        write(&mut written, &records).unwrap();
+        let (_, read_again) = read(serde_json::from_slice(&written).unwrap()).unwrap();
+        assert_eq!(read_again, records);
+        assert_eq!(
+            read_again["a"].segments[0].extra["note"],
+            json!("from the future")
+        );
+    }
+
+    #[test]
+    fn test_newer_versions_are_refused() {
+        let file = json!({ "version": VERSION + 1, "records": {} });
+        assert!(matches!(read(file), Err(SchemaError::Unsupported(v)) if v == VERSION + 1));
+    }
+}
@@ -0,0 +1,451 @@
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Days, Local, Utc};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+
+use crate::log::log;
+use crate::repository::error::{RepositoryError, Result};
+use crate::repository::model::{Extra, ProjectState, TimeKind, TimeSegment, WorkRecord};
+use crate::repository::week::Week;
+use crate::repository::work_record::{later_parts, split_into_days, WorkRecordRepository};
+use crate::time::local_midnight;
+
+/// The schema of version `n + 1` is created by the first `n + 1` migrations, the version of a
+/// database is stored in its `user_version`.
+const MIGRATIONS: [&str; 5] = [
+    "CREATE TABLE IF NOT EXISTS records (
+        id TEXT PRIMARY KEY,
+        name TEXT NOT NULL,
+        start TEXT NOT NULL,
+        end TEXT,
+        state TEXT NOT NULL,
+        last_seen TEXT
+    );
+    CREATE INDEX IF NOT EXISTS records_start ON records (start);
+    CREATE TABLE IF NOT EXISTS segments (
+        record_id TEXT NOT NULL REFERENCES records (id) ON DELETE CASCADE,
+        position INTEGER NOT NULL,
+        start TEXT NOT NULL,
+        end TEXT,
+        kind TEXT NOT NULL,
+        PRIMARY KEY (record_id, position)
+    );
+    CREATE INDEX IF NOT EXISTS segments_start ON segments (start);",
+    // fields unknown to this version, see `Extra`
+    "ALTER TABLE records ADD COLUMN extra TEXT;
+    ALTER TABLE segments ADD COLUMN extra TEXT;",
+    "ALTER TABLE records ADD COLUMN description TEXT;",
+    "CREATE TABLE IF NOT EXISTS tags (
+        record_id TEXT NOT NULL REFERENCES records (id) ON DELETE CASCADE,
+        tag TEXT NOT NULL,
+        PRIMARY KEY (record_id, tag)
+    );",
+    "ALTER TABLE segments ADD COLUMN reason TEXT;",
+];
+
+const SELECT_RECORDS: &str =
+    "SELECT id, name, start, end, state, last_seen, extra, description FROM records";
+
+/// Stores work records in `work_records.sqlite`, with one row per record and segment. Points in
+/// time are stored as UTC text, which sorts chronologically.
+#[derive(Debug)]
+pub struct SqliteRepository {
+    connection: Connection,
+    /// copy of the database taken before it was migrated to the current schema
+    backup: Option<PathBuf>,
+}
+
+impl SqliteRepository {
+    pub fn new(data_dir: &Path) -> Result<SqliteRepository> {
+        std::fs::create_dir_all(data_dir)?;
+        let connection = Connection::open(data_dir.join("work_records.sqlite"))?;
+        // other processes (i.e. commands run while the app is open) use the same database
+        connection.busy_timeout(std::time::Duration::from_secs(5))?;
+        connection.pragma_update(None, "foreign_keys", "ON")?;
+        let mut repository = SqliteRepository {
+            connection,
+            backup: None,
+        };
+        repository.migrate(data_dir)?;
+        Ok(repository)
+    }
+
+    /// Brings the schema up to date. Databases that already contain records are copied to
+    /// `work_records.sqlite.backup-<timestamp>` first.
+    fn migrate(&mut self, data_dir: &Path) -> Result<()> {
+        let version: u32 = self
+            .connection
+            .pragma_query_value(None, "user_version", |row| row.get(0))?;
+        if version as usize > MIGRATIONS.len() {
+            return Err(RepositoryError::Unsupported {
+                path: data_dir.join("work_records.sqlite"),
+                version,
+            });
+        }
+        if version as usize == MIGRATIONS.len() {
+            return Ok(());
+        }
+        let has_records: bool = self.connection.query_row(
+            "SELECT count(*) > 0 FROM sqlite_master WHERE type = 'table' AND name = 'records'",
+            [],
+            |row| row.get(0),
+        )?;
+        if has_records {
+            let backup = data_dir.join(format!(
+                "work_records.sqlite.backup-{}",
+                Local::now().format("%Y%m%dT%H%M%S")
+            ));
+            self.connection
+                .execute("VACUUM INTO ?1", [backup.to_string_lossy()])?;
+            log!(
+                "upgrading the database to schema version {}, the previous one is kept in {}",
+                MIGRATIONS.len(),
+                backup.display()
+            );
+            self.backup = Some(backup);
+        }
+        let transaction = self.connection.transaction()?;
+        for migration in &MIGRATIONS[version as usize..] {
+            transaction.execute_batch(migration)?;
+        }
+        transaction.pragma_update(None, "user_version", MIGRATIONS.len())?;
+        transaction.commit()?;
+        Ok(())
+    }
+
+    /// all records that started in `[from, to)`, ordered by their start
+    fn find_between(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<Vec<WorkRecord>> {
+        let mut statement = self.connection.prepare_cached(&format!(
+            "{SELECT_RECORDS} WHERE start >= ?1 AND start < ?2 ORDER BY start"
+        ))?;
+        let records = statement
+            .query_map(params![from, to], record_of_row)?
+            .collect::<rusqlite::Result<Vec<WorkRecord>>>()?;
+        self.with_segments(records)
+    }
+
+    /// loads the segments and tags of `records`
+    fn with_segments(&self, mut records: Vec<WorkRecord>) -> Result<Vec<WorkRecord>> {
+        let mut segments = self.connection.prepare_cached(
+            "SELECT start, end, kind, extra, reason FROM segments WHERE record_id = ?1
+            ORDER BY position",
+        )?;
+        let mut tags = self
+            .connection
+            .prepare_cached("SELECT tag FROM tags WHERE record_id = ?1")?;
+        for record in records.iter_mut() {
+            record.segments = segments
+                .query_map(params![record.id], segment_of_row)?
+                .collect::<rusqlite::Result<Vec<TimeSegment>>>()?;
+            record.tags = tags
+                .query_map(params![record.id], |row| row.get(0))?
+                .collect::<rusqlite::Result<BTreeSet<String>>>()?;
+        }
+        Ok(records)
+    }
+}
+
+impl WorkRecordRepository for SqliteRepository {
+    fn get_latest(&self) -> Option<WorkRecord> {
+        let latest = self
+            .connection
+            .query_row(
+                &format!("{SELECT_RECORDS} ORDER BY start DESC LIMIT 1"),
+                [],
+                record_of_row,
+            )
+            .optional()
+            .map_err(Into::into)
+            .and_then(|record| match record {
+                Some(record) => self.with_segments(vec![record]).map(|mut r| r.pop()),
+                None => Ok(None),
+            });
+        match latest {
+            Ok(latest) => latest,
+            Err(e) => {
+                log!("⚠ failed to load the latest record: {}", e);
+                None
+            }
+        }
+    }
+
+    fn find_week(&self, start: &dyn Week) -> Result<Vec<WorkRecord>> {
+        let monday = start.first_day();
+        self.find_between(
+            local_midnight(monday),
+            local_midnight(monday + Days::new(7)),
+        )
+    }
+
+    fn persist(&mut self, entity: WorkRecord) -> Result<()> {
+        let transaction = self.connection.transaction()?;
+        let parts = split_into_days(entity);
+        for (id, _) in parts.last().map(later_parts).into_iter().flatten() {
+            if transaction.execute("DELETE FROM records WHERE id = ?1", [&id])? == 0 {
+                break;
+            }
+        }
+        for part in parts {
+            transaction.execute(
+                "INSERT INTO records (id, name, start, end, state, last_seen, extra, description)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+                ON CONFLICT (id) DO UPDATE SET name = ?2, start = ?3, end = ?4, state = ?5,
+                    last_seen = ?6, extra = ?7, description = ?8",
+                params![
+                    part.id,
+                    part.name,
+                    part.start,
+                    part.end,
+                    state_name(&part.state),
+                    part.last_seen,
+                    extra_to_sql(&part.extra)?,
+                    part.description
+                ],
+            )?;
+            transaction.execute("DELETE FROM tags WHERE record_id = ?1", [&part.id])?;
+            for tag in &part.tags {
+                transaction.execute(
+                    "INSERT INTO tags (record_id, tag) VALUES (?1, ?2)",
+                    params![part.id, tag],
+                )?;
+            }
+            transaction.execute("DELETE FROM segments WHERE record_id = ?1", [&part.id])?;
+            for (position, segment) in part.segments.iter().enumerate() {
+                transaction.execute(
+                    "INSERT INTO segments (record_id, position, start, end, kind, extra, reason)
+                    VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                    params![
+                        part.id,
+                        position,
+                        segment.start,
+                        segment.end,
+                        segment.kind.name(),
+                        extra_to_sql(&segment.extra)?,
+                        segment.reason
+                    ],
+                )?;
+            }
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+
+    fn delete(&mut self, entity: &WorkRecord) -> Result<()> {
+        // segments and tags are deleted along with the record
+        let transaction = self.connection.transaction()?;
+        for part in split_into_days(entity.clone()) {
+            transaction.execute("DELETE FROM records WHERE id = ?1", [&part.id])?;
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+
+    fn find_range(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        project: Option<&str>,
+    ) -> Result<Vec<WorkRecord>> {
+        let mut statement = self.connection.prepare_cached(&format!(
+            "{SELECT_RECORDS} WHERE start < ?2 AND (end IS NULL OR end > ?1)
+                AND (?3 IS NULL OR name = ?3) ORDER BY start"
+        ))?;
+        let records = statement
+            .query_map(params![from, to, project], record_of_row)?
+            .collect::<rusqlite::Result<Vec<WorkRecord>>>()?;
+        self.with_segments(records)
+    }
+
+    /// The schema is already migrated when the database is opened.
+    fn upgrade(&mut self) -> Result<Option<PathBuf>> {
+        Ok(self.backup.clone())
+    }
+
+    fn all(&self) -> Result<Vec<WorkRecord>> {
+        let mut statement = self
+            .connection
+            .prepare(&format!("{SELECT_RECORDS} ORDER BY start"))?;
+        let records = statement
+            .query_map([], record_of_row)?
+            .collect::<rusqlite::Result<Vec<WorkRecord>>>()?;
+        self.with_segments(records)
+    }
+}
+
+fn record_of_row(row: &Row) -> rusqlite::Result<WorkRecord> {
+    let state: String = row.get(4)?;
+    Ok(WorkRecord {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        start: row.get(2)?,
+        end: row.get(3)?,
+        state: match state.as_str() {
+            "Working" => ProjectState::Working,
+            "Paused" => ProjectState::Paused,
+            _ => ProjectState::Done,
+        },
+        segments: vec![],
+        last_seen: row.get(5)?,
+        description: row.get(7)?,
+        tags: BTreeSet::new(),
+        extra: extra_of_sql(row, 6)?,
+    })
+}
+
+fn segment_of_row(row: &Row) -> rusqlite::Result<TimeSegment> {
+    let kind: String = row.get(2)?;
+    Ok(TimeSegment {
+        start: row.get(0)?,
+        end: row.get(1)?,
+        kind: TimeKind::from(kind),
+        reason: row.get(4)?,
+        extra: extra_of_sql(row, 3)?,
+    })
+}
+
+fn extra_to_sql(extra: &Extra) -> serde_json::Result<Option<String>> {
+    if extra.is_empty() {
+        return Ok(None);
+    }
+    serde_json::to_string(extra).map(Some)
+}
+
+fn extra_of_sql(row: &Row, index: usize) -> rusqlite::Result<Extra> {
+    let Some(extra) = row.get::<_, Option<String>>(index)? else {
+        return Ok(Extra::new());
+    };
+    serde_json::from_str(&extra).map_err(|e| {
+        rusqlite::Error::FromSqlConversionFailure(index, rusqlite::types::Type::Text, e.into())
+    })
+}
+
+fn state_name(state: &ProjectState) -> &'static str {
+    match state {
+        ProjectState::Working => "Working",
+        ProjectState::Paused => "Paused",
+        ProjectState::Done => "Done",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, TimeZone};
+
+    use super::*;
+
+    fn record(id: &str, start: DateTime<Utc>) -> WorkRecord {
+        let mut record = WorkRecord::new("EKS".to_string(), BTreeSet::new(), start);
+        record.id = id.to_string();
+        record
+            .pause_at(
+                start + Duration::milliseconds(1500),
+                Some("coffee".to_string()),
+            )
+            .unwrap();
+        record.last_seen = Some(start + Duration::minutes(1));
+        record
+    }
+
+    #[test]
+    fn test_persist_and_find() {
+        let directory = tempfile::tempdir().unwrap();
+        let mut repository = SqliteRepository::new(directory.path()).unwrap();
+        let start = Utc.with_ymd_and_hms(2023, 7, 4, 8, 0, 0).unwrap();
+        let mut entity = record("a", start);
+
+        repository.persist(entity.clone()).unwrap();
+        let mut previous = record("previous week", start - Duration::days(7));
+        previous.close_at(previous.start + Duration::hours(1));
+        repository.persist(previous).unwrap();
+        entity.segments.pop();
+        entity.close_at(start + Duration::hours(1));
+        repository.persist(entity.clone()).unwrap();
+
+        assert_eq!(repository.find_week(&start).unwrap(), vec![entity.clone()]);
+        assert_eq!(repository.get_latest(), Some(entity.clone()));
+        assert_eq!(repository.all().unwrap().len(), 2);
+        let records = repository
+            .find_range(start - Duration::days(30), start, None)
+            .unwrap();
+        assert_eq!(records.len(), 1);
+        let records = repository
+            .find_range(start, start + Duration::minutes(30), Some("EKS"))
+            .unwrap();
+        assert_eq!(records, vec![entity.clone()]);
+        let records = repository
+            .find_range(start, start + Duration::minutes(30), Some("Swag"))
+            .unwrap();
+        assert!(records.is_empty());
+
+        repository.delete(&entity).unwrap();
+        assert!(repository.find_week(&start).unwrap().is_empty());
+        let segments: i64 = repository
+            .connection
+            .query_row(
+                "SELECT COUNT(*) FROM segments WHERE record_id = 'a'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(segments, 0);
+    }
+
+    #[test]
+    fn test_persist_and_delete_remove_later_parts() {
+        let directory = tempfile::tempdir().unwrap();
+        let mut repository = SqliteRepository::new(directory.path()).unwrap();
+        let tuesday = chrono::NaiveDate::from_ymd_opt(2023, 7, 4).unwrap();
+        let start = crate::time::parse_local("22:00", tuesday).unwrap();
+        let mut entity = record("a", start);
+        entity.segments.pop();
+        entity.segments[0].end = None;
+        entity.close_at(crate::time::parse_local("2023-07-05 02:00", tuesday).unwrap());
+        repository.persist(entity.clone()).unwrap();
+        assert_eq!(repository.all().unwrap().len(), 2);
+
+        let mut shortened = entity.clone();
+        shortened.segments[0].end = None;
+        shortened.close_at(start + Duration::hours(1));
+        repository.persist(shortened.clone()).unwrap();
+        assert_eq!(repository.all().unwrap(), vec![shortened]);
+
+        repository.persist(entity.clone()).unwrap();
+        repository.delete(&entity).unwrap();
+        assert!(repository.all().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_migrate() {
+        let directory = tempfile::tempdir().unwrap();
+        let connection = Connection::open(directory.path().join("work_records.sqlite")).unwrap();
+        connection.execute_batch(MIGRATIONS[0]).unwrap();
+        connection
+            .execute(
+                "INSERT INTO records (id, name, start, end, state)
+                VALUES ('a', 'EKS', '2023-07-04 08:00:00+00:00', NULL, 'Working')",
+                [],
+            )
+            .unwrap();
+        drop(connection);
+
+        let mut repository = SqliteRepository::new(directory.path()).unwrap();
+        let backup = repository
+            .upgrade()
+            .unwrap()
+            .expect("should back up version 1");
+        assert!(backup.is_file());
+        assert_eq!(repository.get_latest().unwrap().id, "a");
+        let mut entity = record("b", Utc.with_ymd_and_hms(2023, 7, 5, 8, 0, 0).unwrap());
+        entity
+            .extra
+            .insert("priority".to_string(), serde_json::json!("high"));
+        entity.description = Some("reviewed the backlog".to_string());
+        entity.tags = BTreeSet::from(["meeting".to_string(), "review".to_string()]);
+        repository.persist(entity.clone()).unwrap();
+        assert_eq!(repository.get_latest(), Some(entity));
+
+        let mut repository = SqliteRepository::new(directory.path()).unwrap();
+        assert!(repository.upgrade().unwrap().is_none());
+    }
+}
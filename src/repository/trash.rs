@@ -0,0 +1,167 @@
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::repository::error::{RepositoryError, Result};
+use crate::repository::model::WorkRecord;
+use crate::repository::schema;
+
+const TRASH: &str = "trash.json";
+
+/// A record that was deleted at `deleted`.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Trashed {
+    pub record: WorkRecord,
+    pub deleted: DateTime<Utc>,
+    /// the [schema version](schema::VERSION) of `record`
+    #[serde(default = "schema::unversioned")]
+    version: u32,
+}
+
+/// Deleted records, kept next to the database for a while so they can be restored. The trash
+/// doesn't depend on the storage backend, the records in it are not part of the database.
+#[derive(Debug)]
+pub struct Trash {
+    path: PathBuf,
+}
+
+impl Trash {
+    pub fn new(data_dir: &Path) -> Trash {
+        Trash {
+            path: data_dir.join(TRASH),
+        }
+    }
+
+    /// the records in the trash, the latest deleted first
+    pub fn list(&self) -> Result<Vec<Trashed>> {
+        let content = match fs::read_to_string(&self.path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(e) => return Err(e.into()),
+        };
+        let mut entries: Vec<Trashed> = serde_json::from_str(&content)?;
+        if let Some(entry) = entries.iter().find(|e| e.version > schema::VERSION) {
+            return Err(RepositoryError::Unsupported {
+                path: self.path.clone(),
+                version: entry.version,
+            });
+        }
+        entries.sort_by_key(|entry| std::cmp::Reverse(entry.deleted));
+        Ok(entries)
+    }
+
+    pub fn put(&self, record: WorkRecord, deleted: DateTime<Utc>) -> Result<()> {
+        let mut entries = self.list()?;
+        entries.retain(|entry| entry.record.id != record.id);
+        entries.push(Trashed {
+            record,
+            deleted,
+            version: schema::VERSION,
+        });
+        self.save(&entries)
+    }
+
+    /// Removes the record with `id` from the trash, `None` if it isn't in there.
+    pub fn take(&self, id: &str) -> Result<Option<Trashed>> {
+        let mut entries = self.list()?;
+        let Some(index) = entries.iter().position(|entry| entry.record.id == id) else {
+            return Ok(None);
+        };
+        let entry = entries.remove(index);
+        self.save(&entries)?;
+        Ok(Some(entry))
+    }
+
+    /// Removes the records deleted longer than `retention` before `now` for good. Returns how
+    /// many were removed.
+    pub fn purge(&self, retention: Duration, now: DateTime<Utc>) -> Result<usize> {
+        let mut entries = self.list()?;
+        let count = entries.len();
+        entries.retain(|entry| now.signed_duration_since(entry.deleted) <= retention);
+        let removed = count - entries.len();
+        if removed > 0 {
+            self.save(&entries)?;
+        }
+        Ok(removed)
+    }
+
+    /// Replaces the trash with `entries` once they are on disk, like the week files.
+    fn save(&self, entries: &[Trashed]) -> Result<()> {
+        let temp_path = self.path.with_extension("json.tmp");
+        let mut writer = BufWriter::new(File::create(&temp_path)?);
+        serde_json::to_writer(&mut writer, entries)?;
+        writer.flush()?;
+        writer.get_ref().sync_all()?;
+        drop(writer);
+        fs::rename(&temp_path, &self.path)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn test_put_take_and_purge() {
+        let directory = tempfile::tempdir().unwrap();
+        let trash = Trash::new(directory.path());
+        let start = Utc.with_ymd_and_hms(2023, 7, 4, 8, 0, 0).unwrap();
+        let swag = WorkRecord::new("Swag".into(), BTreeSet::new(), start);
+        let eks = WorkRecord::new("EKS".into(), BTreeSet::new(), start);
+        assert!(trash.list().unwrap().is_empty());
+
+        trash.put(swag.clone(), start + Duration::days(1)).unwrap();
+        trash.put(eks.clone(), start + Duration::days(2)).unwrap();
+        let names: Vec<_> = trash
+            .list()
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.record.name)
+            .collect();
+        assert_eq!(names, vec!["EKS", "Swag"]);
+
+        let now = start + Duration::days(32);
+        assert_eq!(trash.purge(Duration::days(30), now).unwrap(), 1);
+        assert_eq!(trash.take(&swag.id).unwrap(), None);
+        let restored = trash.take(&eks.id).unwrap().unwrap();
+        assert_eq!(restored.record, eks);
+        assert!(trash.list().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_versions_of_entries() {
+        let directory = tempfile::tempdir().unwrap();
+        let trash = Trash::new(directory.path());
+        let start = Utc.with_ymd_and_hms(2023, 7, 4, 8, 0, 0).unwrap();
+        let record = WorkRecord::new("EKS".into(), BTreeSet::new(), start);
+        let entry = |version: Option<u32>| {
+            let mut entry = serde_json::json!({ "record": record, "deleted": start });
+            if let Some(version) = version {
+                entry["version"] = version.into();
+            }
+            entry
+        };
+
+        // written before entries had a version
+        fs::write(&trash.path, serde_json::json!([entry(None)]).to_string()).unwrap();
+        assert_eq!(trash.take(&record.id).unwrap().unwrap().record, record);
+
+        fs::write(
+            &trash.path,
+            serde_json::json!([entry(Some(99))]).to_string(),
+        )
+        .unwrap();
+        assert!(matches!(
+            trash.list(),
+            Err(RepositoryError::Unsupported { version: 99, .. })
+        ));
+    }
+}
@@ -1,26 +1,65 @@
-use std::fmt::Display;
-
-use chrono::{DateTime, Datelike, NaiveDate, TimeZone};
+use chrono::{DateTime, Datelike, Local, NaiveDate, TimeZone, Weekday};
 
 /// Trait to abstract over different Date-related types that need to be converted to an ISO-Week
 /// string
 pub trait Week {
-    /// converts this type to a string representation of the format `YYYY-WW` (e.g. 2023-05)
+    /// converts this type to a string representation of the format `YYYY-WW` (e.g. 2023-05),
+    /// where `YYYY` is the ISO week-numbering year, which differs from the calendar year for some
+    /// days around new year
     fn to_week(&self) -> String;
+
+    /// the monday of the week
+    fn first_day(&self) -> NaiveDate {
+        self.to_week()
+            .split_once('-')
+            .and_then(|(year, week)| {
+                NaiveDate::from_isoywd_opt(year.parse().ok()?, week.parse().ok()?, Weekday::Mon)
+            })
+            .expect("weeks are formatted as YYYY-WW")
+    }
 }
 
-impl<Tz: TimeZone> Week for DateTime<Tz>
-where
-    Tz::Offset: Display,
-{
+/// Points in time belong to the week of their date in local time.
+impl<Tz: TimeZone> Week for DateTime<Tz> {
     fn to_week(&self) -> String {
-        format!("{}", self.format("%Y-%U"))
+        self.with_timezone(&Local).date_naive().to_week()
     }
 }
 
 impl Week for NaiveDate {
     fn to_week(&self) -> String {
         let week = self.iso_week();
-        format!("{:4}-{:2}", week.year(), week.week())
+        format!("{:04}-{:02}", week.year(), week.week())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveTime;
+
+    use super::*;
+
+    #[test]
+    fn test_naive_date() {
+        let date = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+        assert_eq!(date(2023, 2, 1).to_week(), "2023-05");
+        // sunday and monday of the same week use different weeks with %U
+        assert_eq!(date(2023, 7, 2).to_week(), "2023-26");
+        assert_eq!(date(2023, 7, 3).to_week(), "2023-27");
+        // days around new year belong to the ISO year
+        assert_eq!(date(2021, 1, 3).to_week(), "2020-53");
+        assert_eq!(date(2021, 1, 3).first_day(), date(2020, 12, 28));
+        assert_eq!(date(2023, 7, 3).first_day(), date(2023, 7, 3));
+        assert_eq!(date(2024, 12, 30).to_week(), "2025-01");
+    }
+
+    #[test]
+    fn test_date_time_uses_local_date() {
+        let date = NaiveDate::from_ymd_opt(2023, 7, 2).unwrap();
+        let late_sunday = Local
+            .from_local_datetime(&date.and_time(NaiveTime::from_hms_opt(23, 30, 0).unwrap()))
+            .unwrap();
+        assert_eq!(late_sunday.to_week(), date.to_week());
+        assert_eq!(late_sunday.with_timezone(&chrono::Utc).to_week(), "2023-26");
     }
 }
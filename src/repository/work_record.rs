@@ -1,119 +1,130 @@
-use std::collections::HashMap;
-use std::fs::{DirEntry, File};
+use std::fmt::Debug;
 use std::path::{Path, PathBuf};
-use std::{fs, io};
 
-use crate::log::log;
+use chrono::{DateTime, Utc};
+
+use crate::app_config::{StorageBackend, StorageConfig};
+use crate::repository::error::{RepositoryError, Result};
+use crate::repository::json::JsonRepository;
 use crate::repository::model::WorkRecord;
+use crate::repository::sqlite::SqliteRepository;
 use crate::repository::week::Week;
+use crate::time::{format_local, next_local_midnight};
 
-#[derive(Debug)]
-pub struct WorkRecordRepository {
-    subfolder: String,
-}
+/// Storage of work records, see [StorageConfig] for the available implementations.
+pub trait WorkRecordRepository: Debug + Send {
+    /// the record that started last
+    fn get_latest(&self) -> Option<WorkRecord>;
 
-impl WorkRecordRepository {
-    pub fn new(path: &str) -> io::Result<WorkRecordRepository> {
-        let root_path = Path::new(&path);
-        create_dir_if_not_exists(root_path)?;
-        let folder = root_path.join(Path::new("work_records/"));
-        create_dir_if_not_exists(&folder)?;
-        let subfolder = folder
-            .into_os_string()
-            .into_string()
-            .expect("this path should always convert");
-        Ok(WorkRecordRepository { subfolder })
-    }
+    /// all records that started in the week of `start`
+    fn find_week(&self, start: &dyn Week) -> Result<Vec<WorkRecord>>;
 
-    pub fn get_latest(&self) -> Option<WorkRecord> {
-        self.load_latest_week().and_then(|entries| {
-            let mut values: Vec<WorkRecord> = entries.into_values().collect();
-            if values.is_empty() {
-                return None;
-            }
-            values.sort_by_key(|x| x.start);
-            Some(values.remove(values.len() - 1))
-        })
-    }
+    /// Saves a record. Finished records that span several days are split at midnight, so every
+    /// part is stored in and counted for the week it belongs to, see [split_into_days]. The parts
+    /// of an earlier version that ran longer are removed, see [later_parts].
+    fn persist(&mut self, entity: WorkRecord) -> Result<()>;
 
-    pub fn find_week(&self, start: &dyn Week) -> io::Result<Vec<WorkRecord>> {
-        let path = self.path_of_week(start);
-        let records: Vec<WorkRecord> = WorkRecordRepository::get_all_of_file(&path)?
-            .into_values()
-            .collect();
-        Ok(records)
-    }
-
-    fn load_latest_week(&self) -> Option<HashMap<String, WorkRecord>> {
-        let iterator: io::Result<Vec<DirEntry>> =
-            fs::read_dir(&self.subfolder).and_then(Iterator::collect);
-        match iterator {
-            Err(err) => {
-                log!(
-                    "failed to list files in database directory ({}): {}",
-                    &self.subfolder,
-                    err
-                );
-                None
-            }
-            Ok(mut iterator) if !iterator.is_empty() => {
-                iterator.sort_by_key(DirEntry::path);
+    /// Removes a record for good, with all the parts it was split into.
+    fn delete(&mut self, entity: &WorkRecord) -> Result<()>;
 
-                iterator
-                    .last()
-                    .map(|entry| (WorkRecordRepository::get_all_of_file(&entry.path()), entry))
-                    .and_then(|(result, entry)| {
-                        if let Err(e) = &result {
-                            log!("failed to load latest file ({:?}): {}", entry.path(), e);
-                        }
-                        result.ok()
-                    })
-            }
-            _ => None,
+    /// Splits `entity` in two at `at`, see [WorkRecord::split_at]. The second part goes to
+    /// `project` if one is given. Returns both parts as they were saved.
+    fn split(
+        &mut self,
+        entity: &WorkRecord,
+        at: DateTime<Utc>,
+        project: Option<&str>,
+    ) -> Result<(WorkRecord, WorkRecord)> {
+        let (first, mut second) = entity.split_at(at).ok_or_else(|| {
+            RepositoryError::Invalid(format!(
+                "{} doesn't run at {}",
+                entity.name,
+                format_local(&at)
+            ))
+        })?;
+        if let Some(project) = project {
+            second.name = project.to_string();
         }
+        self.persist(first.clone())?;
+        self.persist(second.clone())?;
+        Ok((first, second))
     }
 
-    #[allow(dead_code)]
-    pub fn get_by_id(&self, id: &str, date: &dyn Week) -> Option<WorkRecord> {
-        let path = self.path_of_week(date);
-        WorkRecordRepository::get_all_of_file(&path)
-            .ok()
-            .and_then(|mut entries| entries.remove(id))
+    /// Merges `next` into `entity`, see [WorkRecord::merge]. Nothing else may have been worked on
+    /// between the two. Returns the merged record, `next` is deleted.
+    fn merge(&mut self, entity: &WorkRecord, next: &WorkRecord) -> Result<WorkRecord> {
+        let merged = entity
+            .merge(next)
+            .map_err(|e| RepositoryError::Invalid(format!("{} {}", entity.name, e)))?;
+        let end = entity.end.unwrap_or(next.start);
+        if let Some(between) = self
+            .find_range(end, next.start, None)?
+            .into_iter()
+            .find(|other| other.id != entity.id && other.id != next.id)
+        {
+            return Err(RepositoryError::Invalid(format!(
+                "{} was worked on in between",
+                between.name
+            )));
+        }
+        self.persist(merged.clone())?;
+        self.delete(next)?;
+        Ok(merged)
     }
 
-    pub fn persist(&mut self, entity: WorkRecord) -> io::Result<()> {
-        let path = self.path_of_week(&entity.start);
-
-        let mut entries = WorkRecordRepository::get_all_of_file(&path)?;
+    /// All records that overlap `from` to `to`, optionally only those of `project`. Records are
+    /// not clipped to the range.
+    fn find_range(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        project: Option<&str>,
+    ) -> Result<Vec<WorkRecord>>;
 
-        entries.insert(entity.id.clone(), entity);
+    /// every record of the database, in no particular order
+    fn all(&self) -> Result<Vec<WorkRecord>>;
 
-        let file = File::create(&path)?;
-        match serde_json::to_writer(file, &entries) {
-            Ok(_) => Ok(()),
-            Err(e) => Err(e.into()),
-        }
+    /// Records that are stored somewhere they aren't looked up, i.e. in the wrong week file.
+    /// Saving them again moves them to the right place.
+    fn misplaced(&self) -> Result<Vec<WorkRecord>> {
+        Ok(vec![])
     }
 
-    fn get_all_of_file(path: &PathBuf) -> io::Result<HashMap<String, WorkRecord>> {
-        if path.is_file() {
-            let file = File::open(path)?;
-            Ok(serde_json::from_reader(file).expect(""))
-        } else {
-            Ok(HashMap::new())
-        }
-    }
+    /// Rewrites everything stored in an older schema version in the current one. Returns where
+    /// the previous version was backed up to, `None` if everything was up to date.
+    fn upgrade(&mut self) -> Result<Option<PathBuf>>;
+}
 
-    fn path_of_week(&self, date: &dyn Week) -> PathBuf {
-        let filename = format!("{}.json", date.to_week()); // 2023-52.json
-        Path::new(&self.subfolder).join(filename)
-    }
+/// Opens the database in `data_dir` with the backend selected in the config.
+pub fn open(data_dir: &Path, config: &StorageConfig) -> Result<Box<dyn WorkRecordRepository>> {
+    Ok(match config.backend {
+        StorageBackend::Json => Box::new(JsonRepository::new(data_dir)?),
+        StorageBackend::Sqlite => Box::new(SqliteRepository::new(data_dir)?),
+    })
 }
 
-fn create_dir_if_not_exists(path: &Path) -> io::Result<()> {
-    if path.is_dir() {
-        Ok(())
-    } else {
-        fs::create_dir(path)
+/// The parts of `entity` that are stored, finished records are split at every midnight.
+pub fn split_into_days(entity: WorkRecord) -> Vec<WorkRecord> {
+    match entity.end {
+        Some(end) => entity.split_at_midnight(end),
+        None => vec![entity],
     }
 }
+
+/// The ids and starts of the parts after the day of `part` that [split_into_days] stored while
+/// the record ran past midnight. They no longer apply once the record is finished before the
+/// midnight after `part`, repositories remove the ones they still have, up to the first missing.
+pub fn later_parts(part: &WorkRecord) -> impl Iterator<Item = (String, DateTime<Utc>)> {
+    let finished_before_midnight = part
+        .end
+        .is_some_and(|end| end < next_local_midnight(&part.start));
+    let mut id = part.id.clone();
+    let mut start = part.start;
+    std::iter::from_fn(move || {
+        finished_before_midnight.then(|| {
+            start = next_local_midnight(&start);
+            id = WorkRecord::id_of_part(&id, start);
+            (id.clone(), start)
+        })
+    })
+}
@@ -0,0 +1,37 @@
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Tracks whether the process was asked to terminate from the outside, e.g. by `SIGTERM`, a
+/// closed terminal or a closed console window.
+#[derive(Debug, Clone)]
+pub struct Termination {
+    requested: Arc<AtomicBool>,
+}
+
+impl Termination {
+    pub fn register() -> io::Result<Termination> {
+        let requested = Arc::new(AtomicBool::new(false));
+        #[cfg(unix)]
+        for signal in [
+            signal_hook::consts::SIGTERM,
+            signal_hook::consts::SIGHUP,
+            signal_hook::consts::SIGINT,
+        ] {
+            signal_hook::flag::register(signal, Arc::clone(&requested))?;
+        }
+        #[cfg(windows)]
+        crate::win::console::register_console_handler(Arc::clone(&requested))?;
+        Ok(Termination { requested })
+    }
+
+    pub fn is_requested(&self) -> bool {
+        self.requested.load(Ordering::SeqCst)
+    }
+
+    /// Signals that all data was saved and the process may be terminated.
+    pub fn complete(&self) {
+        #[cfg(windows)]
+        crate::win::console::shutdown_completed();
+    }
+}
@@ -0,0 +1,77 @@
+use chrono::{DateTime, Days, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
+
+/// Parses a point in time entered by the user in local time. Accepts either a full date and time
+/// (`2023-07-03 10:15`) or just a time (`10:15`), which is then taken to be on `day`.
+pub fn parse_local(input: &str, day: NaiveDate) -> Option<DateTime<Utc>> {
+    let input = input.trim();
+    let naive = NaiveDateTime::parse_from_str(input, "%Y-%m-%d %H:%M")
+        .ok()
+        .or_else(|| {
+            NaiveTime::parse_from_str(input, "%H:%M")
+                .ok()
+                .map(|time| day.and_time(time))
+        })?;
+    Local
+        .from_local_datetime(&naive)
+        .earliest()
+        .map(|local| local.with_timezone(&Utc))
+}
+
+/// Formats a point in time as local date and time, the counterpart of [parse_local].
+pub fn format_local(time: &DateTime<Utc>) -> String {
+    time.with_timezone(&Local)
+        .format("%Y-%m-%d %H:%M")
+        .to_string()
+}
+
+/// The first moment of `day` in local time.
+pub fn local_midnight(day: NaiveDate) -> DateTime<Utc> {
+    let midnight = day.and_time(NaiveTime::MIN);
+    Local
+        .from_local_datetime(&midnight)
+        .earliest()
+        // some time zones skip midnight when switching to daylight saving time
+        .unwrap_or_else(|| Local.from_utc_datetime(&midnight))
+        .with_timezone(&Utc)
+}
+
+/// The first moment of the local day after the one `time` falls on.
+pub fn next_local_midnight(time: &DateTime<Utc>) -> DateTime<Utc> {
+    let day = time.with_timezone(&Local).date_naive();
+    local_midnight(day + Days::new(1))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Timelike;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_local() {
+        let day = NaiveDate::from_ymd_opt(2023, 7, 3).unwrap();
+
+        let time = parse_local("10:15", day).expect("should parse a time");
+        assert_eq!(time.with_timezone(&Local).date_naive(), day);
+        assert_eq!(time.with_timezone(&Local).hour(), 10);
+        assert_eq!(time.with_timezone(&Local).minute(), 15);
+
+        let time = parse_local(" 2023-07-01 08:00 ", day).expect("should parse date and time");
+        assert_eq!(format_local(&time), "2023-07-01 08:00");
+
+        assert_eq!(parse_local("yesterday", day), None);
+        assert_eq!(parse_local("25:00", day), None);
+    }
+
+    #[test]
+    fn test_next_local_midnight() {
+        let day = NaiveDate::from_ymd_opt(2023, 7, 2).unwrap();
+        let evening = parse_local("2023-07-02 23:00", day).unwrap();
+        let midnight = next_local_midnight(&evening);
+        assert_eq!(format_local(&midnight), "2023-07-03 00:00");
+        assert_eq!(
+            next_local_midnight(&midnight),
+            local_midnight(day + Days::new(2))
+        );
+    }
+}
@@ -11,10 +11,11 @@ use tui::{
 
 use tui::widgets::{Clear, ListState, Paragraph, Table, Wrap};
 
-use crate::app::{App, Focus, Mode};
+use crate::app::{describe, App, Focus, Mode};
 
 use crate::log::LOG;
-use crate::widgets::week_picker::WeekPicker;
+use crate::widgets::period_picker::PeriodPicker;
+use crate::widgets::prompt::{Prompt, PromptState};
 
 pub fn draw<B: Backend>(f: &mut Frame<B>, app: &mut App) {
     draw_screen(f, app, f.size());
@@ -51,6 +52,36 @@ where
     if app.focus == Focus::Report {
         draw_report(f, app, rows[1])
     }
+    if app.focus == Focus::History {
+        draw_history(f, app, rows[1])
+    }
+
+    if let Some(ref mut prompt) = app.prompt {
+        draw_prompt(f, &mut prompt.state, area)
+    }
+}
+
+fn draw_prompt<B>(f: &mut Frame<B>, state: &mut PromptState, area: Rect)
+where
+    B: Backend,
+{
+    let height = match state {
+        PromptState::Choice { options, .. } => options.len() as u16 + 2,
+        PromptState::Text { .. } => 3,
+    };
+    f.render_stateful_widget(Prompt {}, centered(area, 60, height), state);
+}
+
+/// a rectangle of the given size in the center of `area`, shrunk to fit if necessary
+fn centered(area: Rect, width: u16, height: u16) -> Rect {
+    let width = width.min(area.width);
+    let height = height.min(area.height);
+    Rect {
+        x: area.x + (area.width - width) / 2,
+        y: area.y + (area.height - height) / 2,
+        width,
+        height,
+    }
 }
 
 fn draw_report<B>(f: &mut Frame<B>, app: &mut App, area: Rect)
@@ -82,19 +113,87 @@ where
         .block(block)
         .wrap(Wrap { trim: true });
 
-    let picker = WeekPicker {};
+    let picker = PeriodPicker {};
     f.render_widget(Clear, inner);
     f.render_widget(paragraph, inner);
-    f.render_stateful_widget(picker, rows[1], &mut app.report.weekpicker);
+    f.render_stateful_widget(picker, rows[1], &mut app.report.picker);
 
-    if let Some(report) = &app.report.report {
+    if let Some(error) = &app.report.error {
+        let paragraph = Paragraph::new(Span::styled(
+            error.as_str(),
+            Style::default().fg(Color::LightRed),
+        ))
+        .wrap(Wrap { trim: true });
+        f.render_widget(paragraph, rows[2])
+    } else if let Some(report) = &app.report.report {
         let record_rows: Vec<tui::widgets::Row> = report.rows.iter().map(|x| x.into()).collect();
-        let table: Table =
-            Table::new(record_rows).widths(&[Constraint::Length(20), Constraint::Length(20)]);
+        let table: Table = Table::new(record_rows).widths(&[
+            Constraint::Length(20),
+            Constraint::Length(20),
+            Constraint::Length(10),
+            Constraint::Min(0),
+        ]);
         f.render_widget(table, rows[2])
     }
 }
 
+fn draw_history<B>(f: &mut Frame<B>, app: &mut App, area: Rect)
+where
+    B: Backend,
+{
+    let inner = area.inner(&Margin {
+        vertical: 5,
+        horizontal: 10,
+    });
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(2), Constraint::Percentage(100)])
+        .split(inner.inner(&Margin {
+            vertical: 1,
+            horizontal: 2,
+        }));
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(Span::raw(" History "))
+        .style(Style::default().bg(Color::Rgb(0x11, 0x11, 0x15)));
+    f.render_widget(Clear, inner);
+    f.render_widget(block, inner);
+
+    let history = &mut app.history;
+    let header = Paragraph::new(Spans::from(vec![
+        Span::styled(
+            format!(
+                "◀ {} ▶  {:.2}h",
+                history.day.format("%a %Y-%m-%d"),
+                history.total.num_minutes() as f64 / 60.
+            ),
+            Style::default().add_modifier(Modifier::BOLD),
+        ),
+        Span::raw("    ←→: day  ↑↓: record  e: edit  n: new record  S: split  m: merge with next  D: delete  R: restore  esc: close"),
+    ]));
+    f.render_widget(header, rows[0]);
+
+    if let Some(error) = &history.error {
+        let paragraph = Paragraph::new(Span::styled(
+            error.as_str(),
+            Style::default().fg(Color::LightRed),
+        ))
+        .wrap(Wrap { trim: true });
+        f.render_widget(paragraph, rows[1]);
+        return;
+    }
+    let records: Vec<ListItem> = history
+        .records
+        .iter()
+        .map(|record| ListItem::new(Spans::from(Span::raw(describe(record).to_string()))))
+        .collect();
+    let list = List::new(records)
+        .highlight_style(Style::default().add_modifier(Modifier::BOLD))
+        .highlight_symbol("> ");
+    f.render_stateful_widget(list, rows[1], &mut history.selection);
+}
+
 fn draw_header<B>(f: &mut Frame<B>, app: &mut App, area: Rect)
 where
     B: Backend,
@@ -104,13 +203,20 @@ where
         .add_modifier(Modifier::BOLD);
 
     let text = vec![
-        Spans::from(format!("{:?}", app.mode)),
+        Spans::from(vec![
+            Span::styled(app.title, Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(" · "),
+            Span::styled(app.profile.as_str(), Style::default().fg(Color::LightCyan)),
+            Span::raw(format!("    {:?}", app.mode)),
+        ]),
         match app.mode {
             Mode::Normal(_) => Spans::from(vec![
                 Span::styled("/", hotkey),
                 Span::raw(" filter mode    "),
                 Span::styled("⏎", hotkey),
                 Span::raw(" select    "),
+                Span::styled("b", hotkey),
+                Span::raw(" select since    "),
                 Span::styled("q", hotkey),
                 Span::raw(" quit     "),
                 Span::styled("p", hotkey),
@@ -121,16 +227,38 @@ where
                 Span::raw(" stop ✓     "),
                 Span::styled("x", hotkey),
                 Span::raw(" report     "),
+                Span::styled("h", hotkey),
+                Span::raw(" history     "),
                 Span::styled("a", hotkey),
                 Span::raw(format!(
                     " {} auto switch     ",
                     if app.auto_switch { "disable" } else { "enable" }
                 )),
+                Span::styled("P", hotkey),
+                Span::raw(" profile     "),
+                Span::styled("d", hotkey),
+                Span::raw(" describe     "),
+                Span::styled("t", hotkey),
+                Span::raw(" tags     "),
+                Span::styled("k", hotkey),
+                Span::raw(" kind     "),
+                Span::styled("u", hotkey),
+                Span::raw(" undo ↶     "),
+                Span::styled("^r", hotkey),
+                Span::raw(" redo ↷     "),
             ]),
             Mode::Filter(_) => Spans::from(vec![
                 Span::styled("⏎", hotkey),
                 Span::raw(" normal mode    "),
             ]),
+            Mode::Prompt(_) => Spans::from(vec![
+                Span::styled("⏎", hotkey),
+                Span::raw(" confirm    "),
+                Span::styled("esc", hotkey),
+                Span::raw(" cancel    "),
+                Span::styled("↑↓", hotkey),
+                Span::raw(" select    "),
+            ]),
         },
         Spans::from(""),
         if let Some(ref selected) = app.active_project {
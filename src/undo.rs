@@ -0,0 +1,196 @@
+use crate::repository::model::WorkRecord;
+
+/// How many steps can be undone at most.
+const MAX_STEPS: usize = 100;
+
+/// One version of a record replaced by another, `None` if the record didn't exist.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Change {
+    pub before: Option<WorkRecord>,
+    pub after: Option<WorkRecord>,
+}
+
+impl Change {
+    fn id(&self) -> &str {
+        match (&self.before, &self.after) {
+            (Some(record), _) | (None, Some(record)) => &record.id,
+            (None, None) => "",
+        }
+    }
+}
+
+/// Everything an operation of the user changed, e.g. starting a project or editing a record.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Step {
+    pub name: String,
+    pub changes: Vec<Change>,
+    /// the active record before and after
+    pub active: (Option<WorkRecord>, Option<WorkRecord>),
+}
+
+impl Step {
+    /// The step that reverts this one.
+    pub fn reversed(&self) -> Step {
+        Step {
+            name: self.name.clone(),
+            changes: self
+                .changes
+                .iter()
+                .map(|change| Change {
+                    before: change.after.clone(),
+                    after: change.before.clone(),
+                })
+                .collect(),
+            active: (self.active.1.clone(), self.active.0.clone()),
+        }
+    }
+
+    /// Records that a record changed from `before` to `after`. Only the first version before and
+    /// the last one after count, if the same record changes several times.
+    fn changed(&mut self, before: Option<&WorkRecord>, after: Option<&WorkRecord>) {
+        let Some(id) = before.or(after).map(|record| record.id.as_str()) else {
+            return;
+        };
+        match self.changes.iter_mut().find(|change| change.id() == id) {
+            Some(change) => change.after = after.cloned(),
+            None => self.changes.push(Change {
+                before: before.cloned(),
+                after: after.cloned(),
+            }),
+        }
+    }
+
+    /// The active record after the step, in the version the step left it in.
+    pub fn active_after(&self) -> Option<&WorkRecord> {
+        let active = self.active.1.as_ref()?;
+        match self.changes.iter().find(|change| change.id() == active.id) {
+            Some(change) => change.after.as_ref(),
+            None => Some(active),
+        }
+    }
+}
+
+/// The steps that can be undone and those that were undone and can be redone. Changes are
+/// recorded between [UndoStack::begin] and [UndoStack::commit].
+#[derive(Debug, Default)]
+pub struct UndoStack {
+    done: Vec<Step>,
+    undone: Vec<Step>,
+    recording: Option<Step>,
+}
+
+impl UndoStack {
+    /// Starts recording the step `name`, `active` is the active record before it.
+    pub fn begin(&mut self, name: String, active: Option<&WorkRecord>) {
+        let mut step = Step {
+            name,
+            changes: vec![],
+            active: (active.cloned(), None),
+        };
+        step.changed(active, active);
+        self.recording = Some(step);
+    }
+
+    /// whether a step is being recorded, steps can't be nested
+    pub fn is_recording(&self) -> bool {
+        self.recording.is_some()
+    }
+
+    /// Records a change of the step being recorded, see [Step::changed].
+    pub fn changed(&mut self, before: Option<&WorkRecord>, after: Option<&WorkRecord>) {
+        if let Some(step) = &mut self.recording {
+            step.changed(before, after);
+        }
+    }
+
+    /// Finishes the step, `active` is the active record after it. Steps that didn't change
+    /// anything are dropped, every other step can't be redone after it.
+    pub fn commit(&mut self, active: Option<&WorkRecord>) {
+        let Some(mut step) = self.recording.take() else {
+            return;
+        };
+        step.active.1 = active.cloned();
+        step.changed(None, active);
+        step.changes.retain(|change| change.before != change.after);
+        let id = |record: &Option<WorkRecord>| record.as_ref().map(|record| record.id.clone());
+        if step.changes.is_empty() && id(&step.active.0) == id(&step.active.1) {
+            return;
+        }
+        self.done.push(step);
+        if self.done.len() > MAX_STEPS {
+            self.done.remove(0);
+        }
+        self.undone.clear();
+    }
+
+    /// The step to apply to undo the latest one.
+    pub fn undo(&mut self) -> Option<Step> {
+        let step = self.done.pop()?;
+        let reversed = step.reversed();
+        self.undone.push(step);
+        Some(reversed)
+    }
+
+    /// The step to apply to redo the latest undone one.
+    pub fn redo(&mut self) -> Option<Step> {
+        let step = self.undone.pop()?;
+        self.done.push(step.clone());
+        Some(step)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use chrono::{TimeZone, Utc};
+
+    use crate::repository::model::ProjectState;
+
+    use super::*;
+
+    #[test]
+    fn test_undo_and_redo() {
+        let start = Utc.with_ymd_and_hms(2023, 7, 4, 8, 0, 0).unwrap();
+        let hour = |hours: i64| start + chrono::Duration::hours(hours);
+        let swag = WorkRecord::new("Swag".into(), BTreeSet::new(), start);
+        let mut stopped = swag.clone();
+        stopped.transition(ProjectState::Done, hour(1)).unwrap();
+        let eks = WorkRecord::new("EKS".into(), BTreeSet::new(), hour(1));
+        let mut stack = UndoStack::default();
+
+        stack.begin("start EKS".into(), Some(&swag));
+        stack.changed(Some(&swag), Some(&stopped));
+        stack.commit(Some(&eks));
+        stack.begin("nothing".into(), Some(&eks));
+        stack.commit(Some(&eks));
+
+        let undo = stack.undo().unwrap();
+        assert_eq!(undo.name, "start EKS");
+        assert_eq!(undo.active_after(), Some(&swag));
+        assert!(undo.changes.contains(&Change {
+            before: Some(eks.clone()),
+            after: None
+        }));
+        assert_eq!(undo.changes.len(), 2);
+        assert!(stack.undo().is_none());
+
+        let redo = stack.redo().unwrap();
+        assert_eq!(redo.active_after(), Some(&eks));
+        assert!(redo.changes.contains(&Change {
+            before: Some(swag.clone()),
+            after: Some(stopped.clone())
+        }));
+        assert!(stack.redo().is_none());
+        assert!(stack.undo().is_some());
+
+        stack.begin("pause".into(), Some(&swag));
+        let mut paused = swag.clone();
+        paused.pause_at(hour(1), None).unwrap();
+        stack.commit(Some(&paused));
+        assert!(
+            stack.redo().is_none(),
+            "a new step can't be followed by a redo"
+        );
+    }
+}
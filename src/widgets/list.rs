@@ -58,7 +58,7 @@ where
         }
 
         let i = match self.state.selected() {
-            Some(i) if i == 0 => item_count - 1,
+            Some(0) => item_count - 1,
             Some(i) => i - 1,
             None => 0,
         };
@@ -1,2 +1,3 @@
 pub mod list;
-pub mod week_picker;
+pub mod period_picker;
+pub mod prompt;
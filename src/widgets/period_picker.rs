@@ -0,0 +1,160 @@
+use chrono::{Datelike, Days, Months, NaiveDate, Utc, Weekday};
+use tui::buffer::Buffer;
+use tui::layout::Rect;
+use tui::widgets::{Paragraph, StatefulWidget, Widget};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Period {
+    Week,
+    Month,
+    Quarter,
+    /// the days from `first` to `last` (inclusive)
+    Custom {
+        first: NaiveDate,
+        last: NaiveDate,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PeriodPickerState {
+    pub date: NaiveDate,
+    pub period: Period,
+}
+
+impl PeriodPickerState {
+    pub fn increment(&mut self) {
+        match self.period {
+            Period::Week => self.date = self.date + Days::new(7),
+            Period::Month => self.date = self.date + Months::new(1),
+            Period::Quarter => self.date = self.date + Months::new(3),
+            Period::Custom { first, last } => {
+                let length = Days::new(last.signed_duration_since(first).num_days() as u64 + 1);
+                self.set_custom(first + length, last + length);
+            }
+        }
+    }
+
+    pub fn decrement(&mut self) {
+        match self.period {
+            Period::Week => self.date = self.date - Days::new(7),
+            Period::Month => self.date = self.date - Months::new(1),
+            Period::Quarter => self.date = self.date - Months::new(3),
+            Period::Custom { first, last } => {
+                let length = Days::new(last.signed_duration_since(first).num_days() as u64 + 1);
+                self.set_custom(first - length, last - length);
+            }
+        }
+    }
+
+    /// switches from weeks to months to quarters and back to weeks
+    pub fn next_period(&mut self) {
+        self.period = match self.period {
+            Period::Week => Period::Month,
+            Period::Month => Period::Quarter,
+            Period::Quarter | Period::Custom { .. } => Period::Week,
+        };
+    }
+
+    pub fn set_custom(&mut self, first: NaiveDate, last: NaiveDate) {
+        self.date = first;
+        self.period = Period::Custom { first, last };
+    }
+
+    /// the first and the last day of the selected period
+    pub fn start_and_end(&self) -> (NaiveDate, NaiveDate) {
+        let first_of_month = |month: u32| {
+            NaiveDate::from_ymd_opt(self.date.year(), month, 1).expect("month should be valid")
+        };
+        match self.period {
+            Period::Week => (
+                self.date.week(Weekday::Mon).first_day(),
+                self.date.week(Weekday::Mon).last_day(),
+            ),
+            Period::Month => {
+                let first = first_of_month(self.date.month());
+                (first, first + Months::new(1) - Days::new(1))
+            }
+            Period::Quarter => {
+                let first = first_of_month(self.date.month0() / 3 * 3 + 1);
+                (first, first + Months::new(3) - Days::new(1))
+            }
+            Period::Custom { first, last } => (first, last),
+        }
+    }
+}
+
+impl Default for PeriodPickerState {
+    fn default() -> Self {
+        PeriodPickerState {
+            date: Utc::now().date_naive(),
+            period: Period::Week,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct PeriodPicker {}
+
+impl StatefulWidget for PeriodPicker {
+    type State = PeriodPickerState;
+
+    fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
+        let (start, end) = state.start_and_end();
+        let period = match state.period {
+            Period::Week => {
+                let week = state.date.iso_week();
+                format!("week: ← {:4}/{:2} →", week.year(), week.week())
+            }
+            Period::Month => format!("month: ← {} →", state.date.format("%Y-%m")),
+            Period::Quarter => format!(
+                "quarter: ← {} Q{} →",
+                state.date.year(),
+                state.date.month0() / 3 + 1
+            ),
+            Period::Custom { .. } => "range: ← →".to_string(),
+        };
+        let paragraph = Paragraph::new(format!(
+            "select {} ({} - {})    g: week/month/quarter  c: custom range  v: records  d: describe  t: tags  b: by tag  p: breaks",
+            period, start, end
+        ));
+        paragraph.render(area, buf)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn test_start_and_end() {
+        let mut state = PeriodPickerState {
+            date: date(2023, 8, 31),
+            period: Period::Week,
+        };
+        assert_eq!(state.start_and_end(), (date(2023, 8, 28), date(2023, 9, 3)));
+        state.next_period();
+        assert_eq!(state.start_and_end(), (date(2023, 8, 1), date(2023, 8, 31)));
+        state.increment();
+        assert_eq!(state.start_and_end(), (date(2023, 9, 1), date(2023, 9, 30)));
+        state.next_period();
+        assert_eq!(state.start_and_end(), (date(2023, 7, 1), date(2023, 9, 30)));
+        state.decrement();
+        assert_eq!(state.start_and_end(), (date(2023, 4, 1), date(2023, 6, 30)));
+
+        state.set_custom(date(2023, 7, 1), date(2023, 7, 10));
+        state.increment();
+        assert_eq!(
+            state.start_and_end(),
+            (date(2023, 7, 11), date(2023, 7, 20))
+        );
+        state.next_period();
+        assert_eq!(
+            state.start_and_end(),
+            (date(2023, 7, 10), date(2023, 7, 16))
+        );
+    }
+}
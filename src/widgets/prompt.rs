@@ -0,0 +1,149 @@
+use crossterm::event::{KeyCode, KeyEvent};
+use tui::buffer::Buffer;
+use tui::layout::Rect;
+use tui::style::{Color, Modifier, Style};
+use tui::text::{Span, Spans};
+use tui::widgets::{
+    Block, Borders, Clear, List, ListItem, ListState, Paragraph, StatefulWidget, Widget, Wrap,
+};
+
+/// State of a modal prompt, which either asks to pick one of several options or to enter a line
+/// of text.
+#[derive(Debug, Clone)]
+pub enum PromptState {
+    Choice {
+        title: String,
+        options: Vec<String>,
+        selection: ListState,
+    },
+    Text {
+        title: String,
+        input: String,
+    },
+}
+
+impl PromptState {
+    pub fn choice(title: &str, options: Vec<String>) -> PromptState {
+        let mut selection = ListState::default();
+        if !options.is_empty() {
+            selection.select(Some(0));
+        }
+        PromptState::Choice {
+            title: title.to_string(),
+            options,
+            selection,
+        }
+    }
+
+    /// a choice with `current` selected, or the first option if it isn't one of them
+    pub fn choice_selecting(title: &str, options: Vec<String>, current: &str) -> PromptState {
+        let position = options.iter().position(|option| option == current);
+        let mut state = PromptState::choice(title, options);
+        if let (PromptState::Choice { selection, .. }, Some(position)) = (&mut state, position) {
+            selection.select(Some(position));
+        }
+        state
+    }
+
+    pub fn text(title: &str, input: &str) -> PromptState {
+        PromptState::Text {
+            title: title.to_string(),
+            input: input.to_string(),
+        }
+    }
+
+    /// index of the selected option, always `None` for text prompts
+    pub fn selected(&self) -> Option<usize> {
+        match self {
+            PromptState::Choice { selection, .. } => selection.selected(),
+            PromptState::Text { .. } => None,
+        }
+    }
+
+    /// the entered text, or the label of the selected option
+    pub fn value(&self) -> String {
+        match self {
+            PromptState::Choice {
+                options, selection, ..
+            } => selection
+                .selected()
+                .and_then(|index| options.get(index))
+                .cloned()
+                .unwrap_or_default(),
+            PromptState::Text { input, .. } => input.clone(),
+        }
+    }
+
+    pub fn on_input(&mut self, event: &KeyEvent) {
+        match self {
+            PromptState::Choice {
+                options, selection, ..
+            } => {
+                if options.is_empty() {
+                    return;
+                }
+                let last = options.len() - 1;
+                let selected = selection.selected().unwrap_or(0);
+                match event.code {
+                    KeyCode::Up => {
+                        selection.select(Some(if selected == 0 { last } else { selected - 1 }))
+                    }
+                    KeyCode::Down => {
+                        selection.select(Some(if selected >= last { 0 } else { selected + 1 }))
+                    }
+                    _ => {}
+                }
+            }
+            PromptState::Text { input, .. } => match event.code {
+                KeyCode::Char(char) => input.push(char),
+                KeyCode::Backspace => {
+                    input.pop();
+                }
+                _ => {}
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Prompt {}
+
+impl StatefulWidget for Prompt {
+    type State = PromptState;
+
+    fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
+        let title = match state {
+            PromptState::Choice { title, .. } | PromptState::Text { title, .. } => title.clone(),
+        };
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::LightCyan))
+            .title(Span::styled(format!(" {title} "), Style::default()))
+            .style(Style::default().bg(Color::Rgb(0x11, 0x11, 0x15)));
+        Clear.render(area, buf);
+        match state {
+            PromptState::Choice {
+                options, selection, ..
+            } => {
+                let items: Vec<ListItem> = options
+                    .iter()
+                    .map(|option| ListItem::new(Spans::from(Span::raw(option.as_str()))))
+                    .collect();
+                let list = List::new(items)
+                    .block(block)
+                    .highlight_style(Style::default().add_modifier(Modifier::BOLD))
+                    .highlight_symbol("> ");
+                StatefulWidget::render(list, area, buf, selection);
+            }
+            PromptState::Text { input, .. } => {
+                let paragraph = Paragraph::new(Spans::from(vec![
+                    Span::raw(input.as_str()),
+                    Span::styled("_", Style::default().add_modifier(Modifier::SLOW_BLINK)),
+                ]))
+                .block(block)
+                .wrap(Wrap { trim: false });
+                paragraph.render(area, buf);
+            }
+        }
+    }
+}
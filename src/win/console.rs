@@ -0,0 +1,61 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+use once_cell::sync::OnceCell;
+use windows::Win32::Foundation::{BOOL, FALSE, TRUE};
+use windows::Win32::System::Console::{
+    SetConsoleCtrlHandler, CTRL_BREAK_EVENT, CTRL_CLOSE_EVENT, CTRL_C_EVENT, CTRL_LOGOFF_EVENT,
+    CTRL_SHUTDOWN_EVENT,
+};
+
+static REQUESTED: OnceCell<Arc<AtomicBool>> = OnceCell::new();
+static COMPLETED: AtomicBool = AtomicBool::new(false);
+
+/// windows kills the process roughly 5 seconds after a close event, whether we are done or not
+const GRACE_PERIOD: Duration = Duration::from_millis(4500);
+
+/// Sets `requested` once the console is closed, the user logs off or the system shuts down.
+pub fn register_console_handler(requested: Arc<AtomicBool>) -> std::io::Result<()> {
+    if REQUESTED.set(requested).is_err() {
+        return Ok(());
+    }
+    if unsafe { SetConsoleCtrlHandler(Some(on_console_event), TRUE) }.as_bool() {
+        Ok(())
+    } else {
+        Err(std::io::Error::last_os_error())
+    }
+}
+
+/// Lets a pending console close event return, which terminates the process.
+pub fn shutdown_completed() {
+    COMPLETED.store(true, Ordering::SeqCst);
+}
+
+unsafe extern "system" fn on_console_event(ctrl_type: u32) -> BOOL {
+    match ctrl_type {
+        // handled events don't end the process, the main loop stops once it sees the request
+        CTRL_C_EVENT | CTRL_BREAK_EVENT => {
+            request_termination();
+            TRUE
+        }
+        CTRL_CLOSE_EVENT | CTRL_LOGOFF_EVENT | CTRL_SHUTDOWN_EVENT => {
+            request_termination();
+            // returning from the handler of these ends the process, so give the main loop time
+            // to save
+            let started = Instant::now();
+            while !COMPLETED.load(Ordering::SeqCst) && started.elapsed() < GRACE_PERIOD {
+                sleep(Duration::from_millis(50));
+            }
+            TRUE
+        }
+        _ => FALSE,
+    }
+}
+
+fn request_termination() {
+    if let Some(requested) = REQUESTED.get() {
+        requested.store(true, Ordering::SeqCst);
+    }
+}